@@ -0,0 +1,143 @@
+use crate::cache::{cache_key, CacheStorage, KeyedStore};
+use crate::lesson::{Alignment, Lesson};
+
+// fnv-1a, unlike DefaultHasher it gives the same key in every build and browser
+pub fn alignment_key(text: &str, translation: &str) -> String {
+    let hash = [cache_key(text), cache_key(translation)]
+        .join("\0")
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    format!("{:016x}", hash)
+}
+
+// word alignments per sentance and translation, the lesson only gets them on export
+#[derive(Debug)]
+pub struct AlignmentStore {
+    entries: KeyedStore<Vec<Alignment>>,
+}
+
+impl AlignmentStore {
+    pub fn open(storage: CacheStorage) -> Self {
+        AlignmentStore {
+            entries: KeyedStore::open(storage),
+        }
+    }
+
+    pub fn get(&self, text: &str, translation: &str) -> Vec<Alignment> {
+        self.entries
+            .get(&alignment_key(text, translation))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // an empty list forgets the sentance
+    pub fn save(&mut self, text: &str, translation: &str, alignments: Vec<Alignment>) {
+        let alignments = Some(alignments).filter(|alignments| !alignments.is_empty());
+        if self
+            .entries
+            .set(alignment_key(text, translation), alignments)
+        {
+            self.entries.flush();
+        }
+    }
+
+    // keeps the alignments of an imported lesson
+    pub fn remember(&mut self, lesson: &Lesson) {
+        lesson.sentances.iter().for_each(|entry| {
+            entry
+                .translations
+                .variants
+                .iter()
+                .filter(|variant| !variant.alignments.is_empty())
+                .for_each(|variant| {
+                    self.save(&entry.text, &variant.text, variant.alignments.clone())
+                });
+        });
+    }
+
+    pub fn fill(&self, lesson: &mut Lesson) {
+        lesson.sentances.iter_mut().for_each(|entry| {
+            let text = entry.text.clone();
+            entry
+                .translations
+                .variants
+                .iter_mut()
+                .for_each(|variant| variant.alignments = self.get(&text, &variant.text));
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cache::TempFile;
+    use crate::lesson::{LanguagePair, Translations};
+
+    fn alignment(original: &[usize], translation: &[usize]) -> Alignment {
+        Alignment {
+            original: original.iter().copied().collect(),
+            translation: translation.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn keys_are_stable() {
+        assert_eq!(alignment_key("Gut.", "Good."), "4c68ef9ca9da6f3e");
+        assert_eq!(
+            alignment_key(" Gut. ", "Good."),
+            alignment_key("Gut.", "Good.")
+        );
+        assert_ne!(
+            alignment_key("Gut.", "Fine."),
+            alignment_key("Gut.", "Good.")
+        );
+        assert_ne!(alignment_key("ab", "c"), alignment_key("a", "bc"));
+    }
+
+    #[test]
+    fn alignments_persist_and_are_exported() {
+        let file = TempFile::new("alignments");
+        let storage = file.storage();
+        let mut lesson = Lesson::from_text("Der Strom ist billig. Gut.", LanguagePair::default());
+        lesson.sentances[0].translations = Translations::machine([
+            "The current is cheap.".to_string(),
+            "Electricity is cheap.".to_string(),
+        ]);
+
+        let mut store = AlignmentStore::open(storage.clone());
+        store.save(
+            "Der Strom ist billig.",
+            "Electricity is cheap.",
+            vec![alignment(&[0, 1], &[0])],
+        );
+        store.save("Gut.", "Good.", Vec::new());
+        assert_eq!(store.len(), 1);
+
+        let store = AlignmentStore::open(storage.clone());
+        store.fill(&mut lesson);
+        let variants = &lesson.sentances[0].translations.variants;
+        assert!(variants[0].alignments.is_empty());
+        assert_eq!(variants[1].alignments, vec![alignment(&[0, 1], &[0])]);
+
+        let mut imported = AlignmentStore::open(CacheStorage::Memory);
+        imported.remember(&lesson);
+        assert_eq!(
+            imported.get("Der Strom ist billig.", "Electricity is cheap."),
+            vec![alignment(&[0, 1], &[0])]
+        );
+        let mut store = AlignmentStore::open(storage.clone());
+        store.save("Der Strom ist billig.", "Electricity is cheap.", Vec::new());
+        assert!(AlignmentStore::open(storage).is_empty());
+    }
+}
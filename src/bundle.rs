@@ -0,0 +1,390 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheStorage;
+use crate::equivalence::LANGUAGES;
+use crate::lesson::{
+    Alignment, Entry, LanguagePair, Lesson, Translations, Variant, VariantKind, TARGETS,
+};
+use crate::tokenizer::tokenize;
+use crate::types::TypeState;
+
+// bump when a file of the old version can no longer be read the same way
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BundleError {
+    // not json, or fields with the wrong type, serde reports line and column
+    Format(String),
+    Version(Option<u32>),
+    Invalid { at: String, message: String },
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Format(message) => write!(f, "invalid lesson file: {}", message),
+            BundleError::Version(None) => write!(f, "lesson file has no version"),
+            BundleError::Version(Some(version)) => write!(
+                f,
+                "lesson file version {} is not supported, expected {}",
+                version, VERSION
+            ),
+            BundleError::Invalid { at, message } => write!(f, "{}: {}", at, message),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+fn invalid(at: String, message: impl Into<String>) -> BundleError {
+    BundleError::Invalid {
+        at,
+        message: message.into(),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default)]
+    pub title: String,
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BundleVariant {
+    pub kind: VariantKind,
+    pub text: String,
+    #[serde(default)]
+    pub alignments: Vec<Alignment>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BundleSentance {
+    pub text: String,
+    #[serde(default)]
+    pub translations: Vec<BundleVariant>,
+    #[serde(default)]
+    pub preferred: usize,
+}
+
+// a lesson as it is saved and shared, alignments are word indices into the sentance
+// and into the translation variant they belong to
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: u32,
+    pub metadata: Metadata,
+    pub sentances: Vec<BundleSentance>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: Option<u32>,
+}
+
+impl Bundle {
+    pub fn from_lesson(lesson: &Lesson) -> Self {
+        Bundle {
+            version: VERSION,
+            metadata: Metadata {
+                title: lesson.title.clone(),
+                source: lesson.pair.source.clone(),
+                target: lesson.pair.target.clone(),
+            },
+            sentances: lesson
+                .sentances
+                .iter()
+                .map(|entry| BundleSentance {
+                    text: entry.text.to_string(),
+                    translations: entry
+                        .translations
+                        .variants
+                        .iter()
+                        .map(|variant| BundleVariant {
+                            kind: variant.kind,
+                            text: variant.text.to_string(),
+                            alignments: variant.alignments.clone(),
+                        })
+                        .collect(),
+                    preferred: entry.translations.preferred,
+                })
+                .collect(),
+        }
+    }
+
+    // errors name the offending field like sentances[2].translations[0].text
+    pub fn validate(&self) -> Result<(), BundleError> {
+        if self.version != VERSION {
+            return Err(BundleError::Version(Some(self.version)));
+        }
+        // only languages with typing rules can be practiced, and only pairs with a model
+        // can be translated
+        for (at, code, allowed) in [
+            ("metadata.source", &self.metadata.source, &LANGUAGES[..]),
+            ("metadata.target", &self.metadata.target, &TARGETS[..]),
+        ] {
+            if !allowed.contains(&code.as_str()) {
+                return Err(invalid(
+                    at.to_string(),
+                    format!(
+                        "\"{}\" is not supported, expected one of {}",
+                        code,
+                        allowed.join(", ")
+                    ),
+                ));
+            }
+        }
+        if LanguagePair::new(&self.metadata.source, &self.metadata.target).is_none() {
+            return Err(invalid(
+                "metadata.target".to_string(),
+                "a lesson is not translated into its own language",
+            ));
+        }
+        if self.sentances.is_empty() {
+            return Err(invalid(
+                "sentances".to_string(),
+                "a lesson needs at least one sentance",
+            ));
+        }
+        self.sentances
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, sentance)| sentance.validate(&format!("sentances[{}]", index)))
+    }
+
+    pub fn into_lesson(self) -> Lesson {
+        Lesson {
+            title: self.metadata.title,
+            pair: LanguagePair {
+                source: self.metadata.source,
+                target: self.metadata.target,
+            },
+            sentances: self
+                .sentances
+                .into_iter()
+                .map(|sentance| Entry {
+                    text: sentance.text.into(),
+                    translations: Translations {
+                        variants: sentance
+                            .translations
+                            .into_iter()
+                            .map(|variant| Variant {
+                                kind: variant.kind,
+                                text: variant.text.into(),
+                                alignments: variant.alignments,
+                            })
+                            .collect(),
+                        preferred: sentance.preferred,
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+impl BundleSentance {
+    fn validate(&self, at: &str) -> Result<(), BundleError> {
+        if self.text.trim().is_empty() {
+            return Err(invalid(format!("{}.text", at), "sentance is empty"));
+        }
+        if !self.translations.is_empty() && self.preferred >= self.translations.len() {
+            return Err(invalid(
+                format!("{}.preferred", at),
+                format!(
+                    "{} is out of range, there are {} translations",
+                    self.preferred,
+                    self.translations.len()
+                ),
+            ));
+        }
+        let words = TypeState::new(&self.text).data.len();
+        self.translations
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, variant)| {
+                let at = format!("{}.translations[{}]", at, index);
+                if variant.text.trim().is_empty() {
+                    return Err(invalid(format!("{}.text", at), "translation is empty"));
+                }
+                let translation_words = tokenize(&variant.text)
+                    .iter()
+                    .filter(|token| token.is_word())
+                    .count();
+                variant
+                    .alignments
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(index, alignment)| {
+                        let at = format!("{}.alignments[{}]", at, index);
+                        [
+                            ("original", &alignment.original, words),
+                            ("translation", &alignment.translation, translation_words),
+                        ]
+                        .into_iter()
+                        .try_for_each(|(side, indices, count)| {
+                            if indices.is_empty() {
+                                return Err(invalid(
+                                    format!("{}.{}", at, side),
+                                    "an alignment needs words on both sides",
+                                ));
+                            }
+                            match indices.iter().find(|index| **index >= count) {
+                                Some(index) => Err(invalid(
+                                    format!("{}.{}", at, side),
+                                    format!(
+                                        "word {} is out of range, there are {} words",
+                                        index, count
+                                    ),
+                                )),
+                                None => Ok(()),
+                            }
+                        })
+                    })
+            })
+    }
+}
+
+// the version is checked first, so a newer file does not fail on some unknown field
+pub fn load(json: &str) -> Result<Lesson, BundleError> {
+    let header: Header =
+        serde_json::from_str(json).map_err(|error| BundleError::Format(error.to_string()))?;
+    match header.version {
+        Some(VERSION) => {}
+        version => return Err(BundleError::Version(version)),
+    }
+    let bundle: Bundle =
+        serde_json::from_str(json).map_err(|error| BundleError::Format(error.to_string()))?;
+    bundle.validate()?;
+    Ok(bundle.into_lesson())
+}
+
+pub fn save(lesson: &Lesson) -> String {
+    serde_json::to_string_pretty(&Bundle::from_lesson(lesson)).expect("bundles serialize")
+}
+
+// the open lesson is kept as a bundle, so a reload continues with the same text
+pub fn keep(storage: &CacheStorage, lesson: &Lesson) {
+    let bundle = serde_json::to_string(&Bundle::from_lesson(lesson)).expect("bundles serialize");
+    storage.replace(&bundle);
+}
+
+pub fn restore(storage: &CacheStorage) -> Option<Lesson> {
+    load(&storage.load()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cache::TempFile;
+
+    const BUNDLE: &str = r#"{
+        "version": 1,
+        "metadata": {"title": "Strom", "source": "de", "target": "en"},
+        "sentances": [
+            {
+                "text": "Der Strom ist billig.",
+                "preferred": 1,
+                "translations": [
+                    {"kind": "machine", "text": "The current is cheap."},
+                    {
+                        "kind": "idiomatic",
+                        "text": "Electricity is cheap.",
+                        "alignments": [{"original": [0, 1], "translation": [0]}]
+                    }
+                ]
+            },
+            {"text": "Gut."}
+        ]
+    }"#;
+
+    fn error(json: &str) -> String {
+        load(json).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn bundles_round_trip() {
+        let lesson = load(BUNDLE).unwrap();
+        assert_eq!(lesson.title, "Strom");
+        assert_eq!(lesson.sentances.len(), 2);
+        let translations = &lesson.sentances[0].translations;
+        let preferred = translations.preferred().unwrap();
+        assert_eq!(preferred.kind, VariantKind::Idiomatic);
+        assert_eq!(preferred.alignments[0].original.len(), 2);
+        assert!(lesson.sentances[1].translations.is_empty());
+        assert_eq!(load(&save(&lesson)).unwrap(), lesson);
+    }
+
+    #[test]
+    fn open_lessons_are_kept() {
+        let file = TempFile::new("lesson");
+        assert_eq!(restore(&file.storage()), None);
+        let lesson = load(BUNDLE).unwrap();
+        keep(&file.storage(), &lesson);
+        assert_eq!(restore(&file.storage()), Some(lesson));
+    }
+
+    #[test]
+    fn versions_are_checked_first() {
+        assert_eq!(
+            error(r#"{"version": 2, "metadata": {}}"#),
+            "lesson file version 2 is not supported, expected 1"
+        );
+        assert_eq!(error(r#"{"sentances": []}"#), "lesson file has no version");
+        assert!(error("{\"version\": 1,\n").starts_with("invalid lesson file: EOF while parsing"));
+    }
+
+    #[test]
+    fn errors_point_at_the_field() {
+        let json = BUNDLE.replace("\"idiomatic\"", "\"poetic\"");
+        assert!(error(&json).contains("unknown variant `poetic`, expected one of"));
+        let json = BUNDLE.replace("\"de\"", "\"German\"");
+        assert_eq!(
+            error(&json),
+            "metadata.source: \"German\" is not supported, expected one of de, fr, es, pl, nl"
+        );
+        let json = BUNDLE.replace("\"de\"", "\"zz\"");
+        assert_eq!(
+            error(&json),
+            "metadata.source: \"zz\" is not supported, expected one of de, fr, es, pl, nl"
+        );
+        let json = BUNDLE
+            .replace("\"de\"", "\"fr\"")
+            .replace("\"en\"", "\"de\"");
+        assert!(load(&json).is_ok());
+        let json = BUNDLE.replace("\"de\"", "\"en\"");
+        assert_eq!(
+            error(&json),
+            "metadata.source: \"en\" is not supported, expected one of de, fr, es, pl, nl"
+        );
+        let json = BUNDLE.replace("\"en\"", "\"zz\"");
+        assert_eq!(
+            error(&json),
+            "metadata.target: \"zz\" is not supported, expected one of en, de, fr, es, pl, nl"
+        );
+        let json = BUNDLE.replace("\"en\"", "\"de\"");
+        assert_eq!(
+            error(&json),
+            "metadata.target: a lesson is not translated into its own language"
+        );
+        let json = BUNDLE.replace("\"preferred\": 1", "\"preferred\": 2");
+        assert_eq!(
+            error(&json),
+            "sentances[0].preferred: 2 is out of range, there are 2 translations"
+        );
+        let json = BUNDLE.replace("\"translation\": [0]", "\"translation\": [3]");
+        assert_eq!(
+            error(&json),
+            "sentances[0].translations[1].alignments[0].translation: word 3 is out of range, there are 3 words"
+        );
+        let json = BUNDLE.replace("[0, 1]", "[]");
+        assert_eq!(
+            error(&json),
+            "sentances[0].translations[1].alignments[0].original: an alignment needs words on both sides"
+        );
+        let json = BUNDLE.replace("\"Gut.\"", "\" \"");
+        assert_eq!(error(&json), "sentances[1].text: sentance is empty");
+    }
+}
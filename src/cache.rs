@@ -0,0 +1,505 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::lesson::LanguagePair;
+use crate::provider::TranslationProvider;
+use crate::translate::{TranslateError, Variants};
+
+// where cached translations are kept, one json entry per line
+#[derive(Clone, PartialEq, Debug)]
+pub enum CacheStorage {
+    Memory,
+    #[cfg(not(target_arch = "wasm32"))]
+    File(PathBuf),
+    #[cfg(target_arch = "wasm32")]
+    LocalStorage(String),
+}
+
+impl Default for CacheStorage {
+    fn default() -> Self {
+        CacheStorage::named("translations")
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// the cache is best effort, a failed write only means translating the sentance again later
+impl CacheStorage {
+    // name.jsonl next to the binary, or the localStorage key name in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn named(name: &str) -> Self {
+        CacheStorage::File(PathBuf::from(format!("{}.jsonl", name)))
+    }
+    #[cfg(target_arch = "wasm32")]
+    pub fn named(name: &str) -> Self {
+        CacheStorage::LocalStorage(name.to_string())
+    }
+
+    pub(crate) fn load(&self) -> String {
+        match self {
+            CacheStorage::Memory => String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            CacheStorage::File(path) => std::fs::read_to_string(path).unwrap_or_default(),
+            #[cfg(target_arch = "wasm32")]
+            CacheStorage::LocalStorage(key) => local_storage()
+                .and_then(|storage| storage.get_item(key).ok().flatten())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn append(&self, lines: &str) {
+        match self {
+            CacheStorage::Memory => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            CacheStorage::File(path) => {
+                use std::io::Write;
+                let _ = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| file.write_all(lines.as_bytes()));
+            }
+            #[cfg(target_arch = "wasm32")]
+            CacheStorage::LocalStorage(_) => self.replace(&(self.load() + lines)),
+        }
+    }
+
+    pub(crate) fn replace(&self, lines: &str) {
+        match self {
+            CacheStorage::Memory => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            CacheStorage::File(path) => {
+                let _ = std::fs::write(path, lines);
+            }
+            #[cfg(target_arch = "wasm32")]
+            CacheStorage::LocalStorage(key) => {
+                if let Some(storage) = local_storage() {
+                    let _ = storage.set_item(key, lines);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyedLine<K, V> {
+    key: K,
+    value: V,
+}
+
+fn keyed_line<V: Serialize>(key: &str, value: &V) -> String {
+    serde_json::to_string(&KeyedLine { key, value }).expect("stored values serialize") + "\n"
+}
+
+// one value per key, stored as one json line each. a change is only written by flush,
+// which rewrites the whole storage, or by append for values that are only added
+#[derive(Debug)]
+pub struct KeyedStore<V> {
+    storage: CacheStorage,
+    entries: BTreeMap<String, V>,
+}
+
+impl<V: Clone + PartialEq + Serialize + DeserializeOwned> KeyedStore<V> {
+    // lines that do not parse are skipped, later lines win over earlier ones
+    pub fn open(storage: CacheStorage) -> Self {
+        let entries = storage
+            .load()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<KeyedLine<String, V>>(line).ok())
+            .map(|line| (line.key, line.value))
+            .collect();
+        KeyedStore { storage, entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    // None removes the key, returns whether anything changed
+    pub fn set(&mut self, key: String, value: Option<V>) -> bool {
+        match value {
+            None => self.entries.remove(&key).is_some(),
+            Some(value) if self.entries.get(&key) == Some(&value) => false,
+            Some(value) => {
+                self.entries.insert(key, value);
+                true
+            }
+        }
+    }
+
+    pub fn flush(&self) {
+        let lines: String = self
+            .entries
+            .iter()
+            .map(|(key, value)| keyed_line(key, value))
+            .collect();
+        self.storage.replace(&lines);
+    }
+
+    // one write for all values, localStorage can only rewrite its value
+    pub fn append(&mut self, values: Vec<(String, V)>) {
+        let lines: String = values
+            .into_iter()
+            .map(|(key, value)| {
+                let line = keyed_line(&key, &value);
+                self.entries.insert(key, value);
+                line
+            })
+            .collect();
+        if !lines.is_empty() {
+            self.storage.append(&lines);
+        }
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&String, &mut V) -> bool) {
+        self.entries.retain(keep);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// a file in the temp dir that is gone again when the test ends
+#[cfg(test)]
+pub(crate) struct TempFile(pub PathBuf);
+
+#[cfg(test)]
+impl TempFile {
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("typing-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        TempFile(path)
+    }
+
+    pub fn storage(&self) -> CacheStorage {
+        CacheStorage::File(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// translated is the best variant, entries written before variants existed only have that
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub model: String,
+    pub source: String,
+    pub translated: String,
+    #[serde(default)]
+    pub variants: Variants,
+}
+
+impl CacheEntry {
+    fn new(model: &str, source: &str, variants: &[String]) -> Self {
+        CacheEntry {
+            model: model.to_string(),
+            source: source.to_string(),
+            translated: variants.first().cloned().unwrap_or_default(),
+            variants: variants.to_vec(),
+        }
+    }
+
+    fn variants(self) -> Variants {
+        if self.variants.is_empty() {
+            vec![self.translated]
+        } else {
+            self.variants
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+// sentances that only differ in unicode normalization or whitespace share a translation
+pub fn cache_key(source: &str) -> String {
+    source
+        .nfc()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn entry_key(model: &str, source: &str) -> String {
+    format!("{}:{}", model, cache_key(source))
+}
+
+#[derive(Debug)]
+pub struct TranslationCache {
+    entries: KeyedStore<CacheEntry>,
+    stats: CacheStats,
+}
+
+impl TranslationCache {
+    pub fn open(storage: CacheStorage) -> Self {
+        TranslationCache {
+            entries: KeyedStore::open(storage),
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn get(&mut self, model: &str, source: &str) -> Option<Variants> {
+        let translated = self
+            .entries
+            .get(&entry_key(model, source))
+            .cloned()
+            .map(CacheEntry::variants);
+        match translated {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        translated
+    }
+
+    pub fn insert(&mut self, model: &str, source: &str, variants: &[String]) {
+        self.insert_all(model, &[(source, variants)]);
+    }
+
+    pub fn insert_all(&mut self, model: &str, translated: &[(&str, &[String])]) {
+        self.entries.append(
+            translated
+                .iter()
+                .map(|(source, variants)| {
+                    let entry = CacheEntry::new(model, &cache_key(source), variants);
+                    (entry_key(model, source), entry)
+                })
+                .collect(),
+        );
+    }
+
+    // drops the entries of one model, or everything when no model is given
+    pub fn invalidate(&mut self, model: Option<&str>) {
+        self.entries
+            .retain(|_, entry| model.is_some_and(|model| model != entry.model));
+        self.entries.flush();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// asks the wrapped provider only for the sentances that are not cached yet
+#[derive(Clone, Debug)]
+pub struct CachedTranslator<P> {
+    pub provider: P,
+    pub cache: Rc<RefCell<TranslationCache>>,
+}
+
+impl<P: TranslationProvider> TranslationProvider for CachedTranslator<P> {
+    fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+    fn model(&self, pair: &LanguagePair) -> String {
+        self.provider.model(pair)
+    }
+
+    async fn translate(
+        &self,
+        pair: &LanguagePair,
+        sentances: &[&str],
+    ) -> Result<Vec<Variants>, TranslateError> {
+        let model: &str = &self.provider.model(pair);
+        let cached: Vec<Option<Variants>> = {
+            let mut cache = self.cache.borrow_mut();
+            sentances
+                .iter()
+                .map(|sentance| cache.get(model, sentance))
+                .collect()
+        };
+        let missing: Vec<&str> = sentances
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(sentance, _)| *sentance)
+            .collect();
+        let mut translated = if missing.is_empty() {
+            Vec::new()
+        } else {
+            self.provider.translate(pair, &missing).await?
+        };
+        if translated.len() != missing.len() {
+            return Err(TranslateError::Mismatch {
+                sent: missing.len(),
+                received: translated.len(),
+            });
+        }
+        // sentances without a translation are asked for again next time
+        let batch: Vec<(&str, &[String])> = missing
+            .iter()
+            .zip(&translated)
+            .filter(|(_, translated)| !translated.is_empty())
+            .map(|(source, translated)| (*source, translated.as_slice()))
+            .collect();
+        self.cache.borrow_mut().insert_all(model, &batch);
+        translated.reverse();
+        Ok(cached
+            .into_iter()
+            .map(|cached| cached.or_else(|| translated.pop()).unwrap_or_default())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use futures::executor::block_on;
+
+    struct Counting {
+        calls: Cell<usize>,
+    }
+
+    impl TranslationProvider for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+        fn model(&self, pair: &LanguagePair) -> String {
+            format!("counting-{}-{}", pair.source, pair.target)
+        }
+        async fn translate(
+            &self,
+            _pair: &LanguagePair,
+            sentances: &[&str],
+        ) -> Result<Vec<Variants>, TranslateError> {
+            self.calls.set(self.calls.get() + sentances.len());
+            Ok(sentances
+                .iter()
+                .map(|item| vec![item.to_uppercase(), item.to_string()])
+                .collect())
+        }
+    }
+
+    fn cached(storage: CacheStorage) -> CachedTranslator<Counting> {
+        CachedTranslator {
+            provider: Counting {
+                calls: Cell::new(0),
+            },
+            cache: Rc::new(RefCell::new(TranslationCache::open(storage))),
+        }
+    }
+
+    #[test]
+    fn only_missing_sentances_are_translated() {
+        let translator = cached(CacheStorage::Memory);
+        assert_eq!(
+            block_on(translator.translate(&LanguagePair::default(), &["eins", "zwei"])).unwrap(),
+            vec![vec!["EINS", "eins"], vec!["ZWEI", "zwei"]]
+        );
+        assert_eq!(
+            block_on(translator.translate(&LanguagePair::default(), &["zwei", "drei", "eins"]))
+                .unwrap(),
+            vec![
+                vec!["ZWEI", "zwei"],
+                vec!["DREI", "drei"],
+                vec!["EINS", "eins"]
+            ]
+        );
+        assert_eq!(translator.provider.calls.get(), 3);
+        assert_eq!(
+            translator.cache.borrow().stats(),
+            CacheStats { hits: 2, misses: 3 }
+        );
+        block_on(translator.translate(&LanguagePair::new("de", "fr").unwrap(), &["eins"])).unwrap();
+        assert_eq!(translator.provider.calls.get(), 4);
+    }
+
+    #[test]
+    fn keys_are_normalized_and_per_model() {
+        let mut cache = TranslationCache::open(CacheStorage::Memory);
+        cache.insert("marian", "Gute  Bäume\n", &["good trees".to_string()]);
+        assert_eq!(
+            cache.get("marian", " Gute Ba\u{308}ume"),
+            Some(vec!["good trees".to_string()])
+        );
+        assert_eq!(cache.get("offline", "Gute Bäume"), None);
+        cache.insert("offline", "Gute Bäume", &["Gute Bäume".to_string()]);
+        cache.invalidate(Some("marian"));
+        assert_eq!(cache.get("marian", "Gute Bäume"), None);
+        assert_eq!(cache.len(), 1);
+        cache.invalidate(None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn file_storage_persists() {
+        let file = TempFile::new("cache");
+        let storage = file.storage();
+        let translator = cached(storage.clone());
+        block_on(translator.translate(&LanguagePair::default(), &["eins", "zwei"])).unwrap();
+        assert_eq!(std::fs::read_to_string(&file.0).unwrap().lines().count(), 2);
+        assert!(translator
+            .cache
+            .borrow_mut()
+            .get("counting-de-en", "zwei")
+            .is_some());
+        std::fs::write(
+            &file.0,
+            std::fs::read_to_string(&file.0).unwrap()
+                + "not json\n"
+                + r#"{"key": "counting-de-en:drei", "value": {"model": "counting-de-en", "source": "drei", "translated": "THREE"}}"#
+                + "\n",
+        )
+        .unwrap();
+
+        let translator = cached(storage.clone());
+        assert_eq!(translator.cache.borrow().len(), 3);
+        assert_eq!(
+            block_on(translator.translate(&LanguagePair::default(), &["eins", "drei"])).unwrap(),
+            vec![vec!["EINS", "eins"], vec!["THREE"]]
+        );
+        assert_eq!(translator.provider.calls.get(), 0);
+
+        translator.cache.borrow_mut().invalidate(None);
+        assert!(TranslationCache::open(storage).is_empty());
+    }
+
+    #[test]
+    fn keyed_stores_write_on_flush() {
+        let file = TempFile::new("keyed");
+        let mut store = KeyedStore::open(file.storage());
+        assert!(store.set("a".to_string(), Some(1)));
+        assert!(!store.set("a".to_string(), Some(1)));
+        assert!(!store.set("b".to_string(), None));
+        assert!(KeyedStore::<u32>::open(file.storage()).is_empty());
+        store.flush();
+        assert_eq!(KeyedStore::open(file.storage()).get("a"), Some(&1));
+        assert!(store.set("a".to_string(), None));
+        store.flush();
+        assert!(KeyedStore::<u32>::open(file.storage()).is_empty());
+    }
+}
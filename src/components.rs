@@ -1,81 +1,334 @@
+use std::rc::Rc;
+
 use leptos::*;
 
-use crate::types::TypeState;
-use crate::utils::compare;
+use crate::input::{InputAction, SENTINEL};
+use crate::lesson::{Translations, VariantKind};
+use crate::progress::Progress;
+use crate::types::{Cursor, Outcome, TypeResults, TypeState};
+
+fn apply(
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+    action: impl FnOnce(&mut TypeState) -> Outcome,
+) {
+    let mut completed = None;
+    set_store.update(|store| {
+        let outcome = action(store);
+        store.record(web_sys::js_sys::Date::now(), outcome);
+        if outcome == Outcome::Completed {
+            completed = Some(store.results());
+        }
+    });
+    if let (Some(results), Some(on_complete)) = (completed, on_complete) {
+        on_complete(results);
+    }
+}
+
+fn dispatch(
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+    action: InputAction,
+) {
+    match action {
+        InputAction::Type(typed) => {
+            apply(set_store, on_complete, |store| store.type_grapheme(&typed))
+        }
+        InputAction::Backspace => apply(set_store, on_complete, TypeState::backspace),
+        InputAction::DeleteWord => apply(set_store, on_complete, TypeState::delete_word),
+        InputAction::NextWord => apply(set_store, on_complete, TypeState::next_word),
+    }
+}
+
+fn on_keydown(
+    event: &ev::KeyboardEvent,
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+) {
+    let key = event.key();
+    // keys pressed while a dead key or IME is composing are delivered by on_compositionend
+    if event.is_composing() {
+        return;
+    }
+    if let Some(action) =
+        InputAction::from_key(&key, event.ctrl_key(), event.alt_key(), event.meta_key())
+    {
+        event.prevent_default();
+        dispatch(set_store, on_complete, action);
+    }
+}
+
+fn on_beforeinput(
+    event: &ev::InputEvent,
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+) {
+    // non cancelable events still change the value and are picked up by on:input,
+    // composition text is committed by on_compositionend
+    if !event.cancelable() || event.is_composing() {
+        return;
+    }
+    event.prevent_default();
+    InputAction::from_input(&event.input_type(), event.data().as_deref())
+        .into_iter()
+        .for_each(|action| dispatch(set_store, on_complete, action));
+}
+
+fn on_input(
+    event: &ev::Event,
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+    composing: bool,
+) {
+    if composing {
+        return;
+    }
+    let input = event_target::<web_sys::HtmlInputElement>(event);
+    let value = input.value();
+    InputAction::from_value(&value)
+        .into_iter()
+        .for_each(|action| dispatch(set_store, on_complete, action));
+    input.set_value(SENTINEL);
+}
+
+fn on_compositionend(
+    event: &ev::CompositionEvent,
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+) {
+    let composed = event.data().unwrap_or_default();
+    set_store.update(|store| store.composition = None);
+    InputAction::from_text(&composed)
+        .into_iter()
+        .for_each(|action| dispatch(set_store, on_complete, action));
+    event_target::<web_sys::HtmlInputElement>(event).set_value(SENTINEL);
+}
+
+pub(crate) fn pending_at(
+    store: ReadSignal<TypeState>,
+    word_index: usize,
+    char_index: usize,
+) -> impl Fn() -> Option<View> {
+    move || {
+        store
+            .with(|store| {
+                let cursor = Cursor {
+                    word_index,
+                    char_index,
+                };
+                store
+                    .composition
+                    .clone()
+                    .or_else(|| store.pending().map(str::to_string))
+                    .filter(|_| store.cursor() == Some(cursor))
+            })
+            .map(|pending| {
+                view! {
+                    <div class="absolute -top-0 -right-0 text-blue-600 italic text-base md:text-3xl">
+                        <p>{pending}</p>
+                    </div>
+                }
+                .into_view()
+            })
+    }
+}
+
+#[component]
+pub(crate) fn TypingInput(
+    store: ReadSignal<TypeState>,
+    set_store: WriteSignal<TypeState>,
+    on_complete: Option<Callback<TypeResults>>,
+    node_ref: NodeRef<html::Input>,
+) -> impl IntoView {
+    let composing = move || store.with_untracked(|store| store.composition.is_some());
+    view! {
+        <input
+            node_ref=node_ref
+            class="absolute top-0 left-0 w-px h-px opacity-0 text-base"
+            type="text"
+            autocomplete="off"
+            autocorrect="off"
+            autocapitalize="off"
+            spellcheck="false"
+            value=SENTINEL
+            on:keydown=move |event| on_keydown(&event, set_store, on_complete)
+            on:beforeinput=move |event| on_beforeinput(&event, set_store, on_complete)
+            on:input=move |event| on_input(&event, set_store, on_complete, composing())
+            on:compositionstart=move |_event| {
+                set_store.update(|store| store.composition = Some(String::new()))
+            }
+
+            on:compositionupdate=move |event| {
+                set_store.update(|store| store.composition = event.data())
+            }
+
+            on:compositionend=move |event| on_compositionend(&event, set_store, on_complete)
+            on:focusin=move |_event| { set_store.update(|store| store.focus = true) }
+            on:focusout=move |_event| { set_store.update(|store| store.focus = false) }
+        />
+    }
+}
+
+pub(crate) fn focus_input(input_ref: NodeRef<html::Input>) {
+    if let Some(input) = input_ref.get_untracked() {
+        let _ = input.focus();
+    }
+}
+
 #[component]
 pub fn Sentance(
-    text: &'static str,
-    translation: &'static str,
-    display: Option<WriteSignal<Option<(&'static str, &'static str)>>>,
+    state: RwSignal<TypeState>,
+    #[prop(into)] translations: Signal<Translations>,
+    // called with the translation that is shown
+    display: Option<Callback<Rc<str>>>,
+    #[prop(optional)] on_complete: Option<Callback<TypeResults>>,
+    #[prop(optional)] on_translations: Option<Callback<Translations>>,
+    #[prop(optional)] node_ref: NodeRef<html::Div>,
 ) -> impl IntoView {
-    let (store, set_store) = create_signal(TypeState::from_str(text));
+    let (store, set_store) = (state.read_only(), state.write_only());
+    let input_ref = create_node_ref::<html::Input>();
+    // the variant being looked at, the preferred one until the learner toggles
+    let (shown, set_shown) = create_signal(None::<usize>);
+    let (kind, set_kind) = create_signal(VariantKind::User);
+    let shown_index = move || {
+        translations.with(|translations| {
+            shown()
+                .filter(|index| *index < translations.variants.len())
+                .unwrap_or(translations.preferred)
+        })
+    };
+    let variant = move || {
+        translations.with(|translations| {
+            translations
+                .variants
+                .get(shown_index())
+                .or_else(|| translations.preferred())
+                .cloned()
+        })
+    };
+    let save = move |changed: Translations| {
+        if let Some(on_translations) = on_translations {
+            on_translations(changed);
+        }
+    };
+    let controls = move || {
+        let count = translations.with(|translations| translations.variants.len());
+        let toggle = (count > 0).then(|| {
+            let preferred = move || translations.with(|translations| translations.preferred) == shown_index();
+            view! {
+                <button on:click=move |_event| set_shown(Some((shown_index() + 1) % count))>
+                    {move || {
+                        variant()
+                            .map(|variant| format!("{} {}/{}", variant.kind.name(), shown_index() + 1, count))
+                    }}
+                </button>
+                <button on:click=move |_event| {
+                    let mut changed = translations.get_untracked();
+                    changed.prefer(shown_index());
+                    save(changed);
+                }>
+                    {move || if preferred() { "★ preferred" } else { "☆ prefer" }}
+                </button>
+            }
+        });
+        on_translations.map(|_| {
+            view! {
+                <div
+                    class="px-8 flex flex-wrap gap-4 text-xl lg:text-base text-gray-600"
+                    on:click=|event| event.stop_propagation()
+                >
+                    {toggle}
+                    <select on:change=move |event| {
+                        if let Some(kind) = VariantKind::from_name(&event_target_value(&event)) {
+                            set_kind(kind)
+                        }
+                    }>
+                        {[VariantKind::User, VariantKind::Literal, VariantKind::Idiomatic]
+                            .into_iter()
+                            .map(|kind| view! { <option value=kind.name()>{kind.name()}</option> })
+                            .collect_view()}
+                    </select>
+                    <input
+                        type="text"
+                        placeholder="add a translation"
+                        on:keydown=move |event| {
+                            if event.key() != "Enter" {
+                                return;
+                            }
+                            let input = event_target::<web_sys::HtmlInputElement>(&event);
+                            let mut changed = translations.get_untracked();
+                            if let Some(index) = changed.add(kind.get_untracked(), &input.value()) {
+                                save(changed);
+                                set_shown(Some(index));
+                            }
+                            input.set_value("");
+                        }
+                    />
+                </div>
+            }
+        })
+    };
+    // the result card of a completed sentance
+    let summary = move || {
+        store
+            .with(|store| store.is_completed().then(|| (store.results(), store.stats())))
+            .map(|(results, stats)| {
+                view! {
+                    <div class="mx-8 my-2 p-3 grid grid-cols-2 lg:grid-cols-4 gap-2 bg-gray-100 text-2xl lg:text-xl text-gray-700">
+                        <div>{format!("{:.0} wpm", stats.net_wpm)}</div>
+                        <div>{format!("{:.0} gross wpm", stats.gross_wpm)}</div>
+                        <div>{format!("{:.0}% accuracy", stats.accuracy * 100.0)}</div>
+                        <div>{format!("{:.1}s", stats.elapsed.as_secs_f64())}</div>
+                        <div>
+                            {format!(
+                                "{} exact, {} lenient, {} wrong of {}",
+                                results.correct,
+                                results.lenient,
+                                results.wrong,
+                                results.total(),
+                            )}
+                        </div>
+                        <div>
+                            {format!(
+                                "{} corrected, {} uncorrected errors",
+                                stats.corrected,
+                                stats.uncorrected,
+                            )}
+                        </div>
+                    </div>
+                }
+            })
+    };
+    let reset = move || {
+        (!store.with(Progress::of).is_empty()).then(|| {
+            view! {
+                <button
+                    class="px-8 text-xl lg:text-base text-gray-600"
+                    on:click=move |event| {
+                        event.stop_propagation();
+                        set_store.update(TypeState::reset);
+                    }
+                >
+                    "reset"
+                </button>
+            }
+        })
+    };
     view! {
         <div class="flex items-center min-h-lvh lg:h-min snap-start">
             <div
+                node_ref=node_ref
                 on:click=move |_| {
-                    if let Some(action) = display {
-                        action(Some((text, translation)))
+                    if let Some(display) = display {
+                        display(variant().map(|variant| variant.text).unwrap_or_else(|| "".into()))
                     }
                 }
 
-                class="p-3 flex flex-wrap text-5xl lg:text-3xl text-gray-500 focus:bg-gray-300 font-mono"
+                class="relative p-3 flex flex-wrap text-5xl lg:text-3xl text-gray-500 focus-within:bg-gray-300 font-mono"
                 tabindex=1
-                on:keydown=move |event| {
-                    let key = event.key_code();
-                    let mut local_store = store.get();
-                    logging::log!("key down {}", key);
-                    let word = local_store.data.get_mut(local_store.word_index).unwrap();
-                    if key == 8 {
-                        if word.char_index > 0 {
-                            word.char_index -= 1;
-                            let temp = word.data.get_mut(word.char_index).unwrap();
-                            temp.backspace();
-                        } else if local_store.word_index > 0 {
-                            local_store.word_index -= 1;
-                        }
-                        set_store(local_store);
-                    } else if (key == 32) && local_store.word_index < local_store.data.len() {
-                        event.prevent_default();
-                        local_store.word_index += 1;
-                        set_store(local_store);
-                    }
-                }
-
-                on:focus=move |_event| { set_store.update(|store| store.focus = true) }
-
-                on:focusout=move |_event| { set_store.update(|store| store.focus = false) }
-
-                on:keypress=move |event| {
-                    let key = event.key_code();
-                    match key {
-                        (64..=93) | (97..=122) | 44 | 45 | 46 | 58 | 59 => {
-                            let mut local_store = store.get();
-                            if local_store.word_index < local_store.data.len() {
-                                logging::log!("current index {}", local_store.word_index);
-                                logging::log!(
-                                    "current word index {}", local_store.data.get(local_store
-                                    .word_index).unwrap().char_index
-                                );
-                                let word = local_store
-                                    .data
-                                    .get_mut(local_store.word_index)
-                                    .unwrap();
-                                if word.char_index < word.data.len() {
-                                    logging::log!("inserting {}", char::from_u32(key).unwrap());
-                                    word.data
-                                        .get_mut(word.char_index)
-                                        .unwrap()
-                                        .typed(char::from_u32(key).unwrap());
-                                    word.char_index += 1;
-                                    set_store(local_store);
-                                }
-                            }
-                        }
-                        _ => {}
-                    };
-                    logging::log!("keypress {:?}", & key);
-                }
+                on:focus=move |_event| focus_input(input_ref)
             >
+                <TypingInput store=store set_store=set_store on_complete=on_complete node_ref=input_ref/>
 
                 {
                     let current_word = move |index| index == store.get_untracked().word_index;
@@ -85,7 +338,7 @@ pub fn Sentance(
                             each=move || store.get().data.into_iter().enumerate()
                             key=move |(index, c)| {
                                 let marker = if current_word(*index) { "selected" } else { "" };
-                                format!("{}-{}-{}-{}", index, c.char_index, marker, focus())
+                                format!("{}-{}-{}-{}", index, c.key(), marker, focus())
                             }
 
                             children=move |(word_index, c)| {
@@ -94,35 +347,54 @@ pub fn Sentance(
                                         <For
                                             each=move || c.clone().data.into_iter().enumerate()
                                             key=|(index, c)| {
-                                                format!("{}-{}", index, c.typed_char.unwrap_or('~'))
+                                                format!("{}-{}-{:?}", index, c.typed_text(), c.quality)
                                             }
 
-                                            children=move |(_index, c)| {
-                                                if let Some(typed_char) = c.typed_char {
-                                                    if compare(typed_char, c.reference_char) {
+                                            children=move |(index, c)| {
+                                                if c.typed.is_some() {
+                                                    if c.is_lenient() {
+                                                        let class = if current_word(word_index) {
+                                                            "relative min-w-4 text-amber-700 underline"
+                                                        } else {
+                                                            "relative min-w-4 text-amber-700"
+                                                        };
+                                                        return view! {
+                                                            <div class=class>
+                                                                {c.reference.clone()}
+                                                                <div class="absolute -top-0 -right-0 text-amber-500 italic text-base md:text-3xl">
+                                                                    <p>{c.typed_text()}</p>
+                                                                </div>
+                                                            </div>
+                                                        };
+                                                    } else if c.is_correct() {
                                                         let class = if current_word(word_index) {
                                                             "min-w-4 text-gray-900 underline"
                                                         } else {
                                                             "min-w-4 text-gray-900"
                                                         };
-                                                        return view! { <div class=class>{c.reference_char}</div> };
+                                                        return view! { <div class=class>{c.reference.clone()}</div> };
                                                     } else {
                                                         return view! {
                                                             <div class="relative text-gray-400 min-w-4 underline">
-                                                                {c.reference_char}
+                                                                {c.reference.clone()}
                                                                 <div class="absolute -top-0 -right-0 text-red-600 italic text-base md:text-3xl">
-                                                                    <p>{c.typed_char}</p>
+                                                                    <p>{c.typed_text()}</p>
                                                                 </div>
                                                             </div>
                                                         };
                                                     }
                                                 }
                                                 let class = if current_word(word_index) && focus() {
-                                                    "min-w-4 underline"
+                                                    "relative min-w-4 underline"
                                                 } else {
-                                                    "min-w-4"
+                                                    "relative min-w-4"
                                                 };
-                                                view! { <div class=class>{c.reference_char}</div> }
+                                                view! {
+                                                    <div class=class>
+                                                        {c.reference.clone()}
+                                                        {pending_at(store, word_index, index)}
+                                                    </div>
+                                                }
                                             }
                                         />
 
@@ -133,9 +405,15 @@ pub fn Sentance(
                     }
                 }
 
+                {summary}
+                {reset}
                 <div class="px-8 p-5 flex flex-wrap text-4xl lg:text-3xl text-gray-500 italic">
-                    {translation}
+                    {move || match variant() {
+                        Some(variant) => variant.text.to_string().into_view(),
+                        None => view! { <span class="text-gray-400">"translation pending"</span> }.into_view(),
+                    }}
                 </div>
+                {controls}
             </div>
         </div>
     }
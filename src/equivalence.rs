@@ -0,0 +1,336 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+pub const LANGUAGES: [&str; 5] = ["de", "fr", "es", "pl", "nl"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Leniency {
+    Strict,
+    CaseInsensitive,
+    #[default]
+    DiacriticInsensitive,
+}
+
+impl Leniency {
+    pub const ALL: [Leniency; 3] = [
+        Leniency::Strict,
+        Leniency::CaseInsensitive,
+        Leniency::DiacriticInsensitive,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Leniency::Strict => "strict",
+            Leniency::CaseInsensitive => "case-insensitive",
+            Leniency::DiacriticInsensitive => "diacritic-insensitive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    #[default]
+    Nfc,
+    Nfd,
+}
+
+impl Normalization {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfd => text.nfd().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Match {
+    Exact,
+    CaseLenient,
+    DiacriticLenient,
+    Wrong,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Transliteration {
+    Complete,
+    Partial,
+    None,
+}
+
+// pairs map a reference char to the typed chars accepted for it on top of the level,
+// transliterations map it to sequences of typed chars that stand for it, like "ae" for "ä",
+// strict accepts only the exact char and ignores both
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Equivalence {
+    pub language: String,
+    #[serde(default)]
+    pub level: Leniency,
+    #[serde(default)]
+    pub pairs: BTreeMap<char, String>,
+    #[serde(default)]
+    pub transliterations: BTreeMap<char, Vec<String>>,
+    #[serde(default)]
+    pub normalization: Normalization,
+}
+
+impl Default for Equivalence {
+    fn default() -> Self {
+        Self::builtin("de").unwrap()
+    }
+}
+
+impl Equivalence {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    // a table of the user replaces the builtin one of its language
+    pub fn custom(json: &str) -> Result<Self, String> {
+        let rules =
+            Self::from_json(json).map_err(|error| format!("invalid typing rules: {}", error))?;
+        if !LANGUAGES.contains(&rules.language.as_str()) {
+            return Err(format!(
+                "typing rules for \"{}\" are not supported, expected one of {}",
+                rules.language,
+                LANGUAGES.join(", ")
+            ));
+        }
+        Ok(rules)
+    }
+
+    pub fn builtin(language: &str) -> Option<Self> {
+        let json = match language {
+            "de" => include_str!("../rules/de.json"),
+            "fr" => include_str!("../rules/fr.json"),
+            "es" => include_str!("../rules/es.json"),
+            "pl" => include_str!("../rules/pl.json"),
+            "nl" => include_str!("../rules/nl.json"),
+            _ => return None,
+        };
+        Some(Self::from_json(json).expect("builtin rules are valid json"))
+    }
+
+    pub fn with_level(mut self, level: Leniency) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        self.normalization.apply(text)
+    }
+
+    pub fn matches(&self, typed: &str, reference: &str) -> bool {
+        self.quality(typed, reference) != Match::Wrong
+    }
+
+    pub fn quality(&self, typed: &str, reference: &str) -> Match {
+        let typed = self.normalize(typed);
+        let reference = self.normalize(reference);
+        if typed == reference {
+            Match::Exact
+        } else if self.level == Leniency::Strict {
+            Match::Wrong
+        } else if typed.to_lowercase() == reference.to_lowercase() {
+            Match::CaseLenient
+        } else if (self.level == Leniency::DiacriticInsensitive && base(&typed) == base(&reference))
+            || self.paired(&typed, &reference)
+        {
+            Match::DiacriticLenient
+        } else {
+            Match::Wrong
+        }
+    }
+
+    fn paired(&self, typed: &str, reference: &str) -> bool {
+        match (single(typed), single(reference)) {
+            (Some(typed), Some(reference)) => self
+                .pairs
+                .get(&reference)
+                .is_some_and(|accepted| accepted.contains(typed)),
+            _ => false,
+        }
+    }
+
+    pub fn transliterate(&self, typed: &str, reference: &str) -> Transliteration {
+        let Some(reference) = single(reference) else {
+            return Transliteration::None;
+        };
+        if self.level == Leniency::Strict {
+            return Transliteration::None;
+        }
+        let typed = self.normalize(typed).to_lowercase();
+        self.transliterations
+            .get(&reference)
+            .or_else(|| self.transliterations.get(&fold(reference)))
+            .into_iter()
+            .flatten()
+            .map(|sequence| self.normalize(sequence).to_lowercase())
+            .fold(Transliteration::None, |result, sequence| {
+                if sequence == typed {
+                    Transliteration::Complete
+                } else if sequence.starts_with(&typed) && result == Transliteration::None {
+                    Transliteration::Partial
+                } else {
+                    result
+                }
+            })
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn base(text: &str) -> String {
+    text.to_lowercase()
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect()
+}
+
+// the single precomposed char of a grapheme, if it has one
+fn single(text: &str) -> Option<char> {
+    let mut chars = text.nfc();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_tables_parse() {
+        LANGUAGES.iter().for_each(|language| {
+            let rules = Equivalence::builtin(language).unwrap();
+            assert_eq!(&rules.language, language);
+        });
+        assert_eq!(Equivalence::builtin("xx"), None);
+    }
+
+    #[test]
+    fn levels() {
+        let de = Equivalence::default();
+        assert!(de.matches("u", "Ü"));
+        assert!(de.matches("s", "ß"));
+        assert!(de.matches("-", "–"));
+        assert!(!de.matches("x", "ß"));
+
+        let case = de.clone().with_level(Leniency::CaseInsensitive);
+        assert!(case.matches("ü", "Ü"));
+        assert!(!case.matches("u", "ü"));
+        assert!(case.matches("s", "ß"));
+
+        let strict = de.with_level(Leniency::Strict);
+        assert!(strict.matches("ä", "ä"));
+        assert!(!strict.matches("a", "A"));
+        assert!(!strict.matches("s", "ß"));
+    }
+
+    #[test]
+    fn other_languages() {
+        let fr = Equivalence::builtin("fr").unwrap();
+        assert!(fr.matches("e", "é"));
+        assert!(fr.matches("c", "Ç"));
+        let es = Equivalence::builtin("es").unwrap();
+        assert!(es.matches("n", "ñ"));
+        assert!(es.matches("?", "¿"));
+        let pl = Equivalence::builtin("pl").unwrap();
+        assert!(pl.matches("l", "ł"));
+        assert!(pl.matches("z", "ż"));
+        let nl = Equivalence::builtin("nl").unwrap();
+        assert!(nl.matches("e", "ë"));
+        assert_eq!(nl.transliterate("ij", "ĳ"), Transliteration::Complete);
+    }
+
+    #[test]
+    fn custom_pairs_from_json() {
+        let rules =
+            Equivalence::from_json(r#"{"language": "nl", "level": "strict", "pairs": {"ĳ": "j"}}"#)
+                .unwrap();
+        assert_eq!(rules.level, Leniency::Strict);
+        assert!(!rules.matches("j", "ĳ"));
+        assert!(rules
+            .clone()
+            .with_level(Leniency::CaseInsensitive)
+            .matches("j", "ĳ"));
+        assert!(Equivalence::from_json(r#"{"level": "loose"}"#).is_err());
+    }
+
+    #[test]
+    fn custom_tables() {
+        let rules = Equivalence::custom(
+            r#"{"language": "de", "pairs": {"ß": "z"}, "transliterations": {"ö": ["oh"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(rules.quality("z", "ß"), Match::DiacriticLenient);
+        assert_eq!(rules.transliterate("oh", "ö"), Transliteration::Complete);
+        let strict = rules.with_level(Leniency::Strict);
+        assert_eq!(strict.quality("z", "ß"), Match::Wrong);
+        assert_eq!(strict.transliterate("oh", "ö"), Transliteration::None);
+        assert!(Equivalence::custom("{")
+            .unwrap_err()
+            .starts_with("invalid typing rules: "));
+        assert_eq!(
+            Equivalence::custom(r#"{"language": "xx"}"#).unwrap_err(),
+            "typing rules for \"xx\" are not supported, expected one of de, fr, es, pl, nl"
+        );
+    }
+
+    #[test]
+    fn quality() {
+        let de = Equivalence::default();
+        assert_eq!(de.quality("ä", "ä"), Match::Exact);
+        assert_eq!(de.quality("d", "D"), Match::CaseLenient);
+        assert_eq!(de.quality("a", "ä"), Match::DiacriticLenient);
+        assert_eq!(de.quality("u", "Ü"), Match::DiacriticLenient);
+        assert_eq!(de.quality("s", "ß"), Match::DiacriticLenient);
+        assert_eq!(de.quality("x", "ß"), Match::Wrong);
+        let case = de.with_level(Leniency::CaseInsensitive);
+        assert_eq!(case.quality("a", "ä"), Match::Wrong);
+    }
+
+    #[test]
+    fn transliterations() {
+        let de = Equivalence::default();
+        assert_eq!(de.transliterate("a", "ä"), Transliteration::Partial);
+        assert_eq!(de.transliterate("ae", "ä"), Transliteration::Complete);
+        assert_eq!(de.transliterate("AE", "Ä"), Transliteration::Complete);
+        assert_eq!(de.transliterate("ss", "ß"), Transliteration::Complete);
+        assert_eq!(de.transliterate("ax", "ä"), Transliteration::None);
+        assert_eq!(de.transliterate("a", "a"), Transliteration::None);
+        let strict = de.with_level(Leniency::Strict);
+        assert_eq!(strict.transliterate("a", "ä"), Transliteration::None);
+        let fr = Equivalence::builtin("fr").unwrap();
+        assert_eq!(fr.transliterate("oe", "œ"), Transliteration::Complete);
+    }
+
+    #[test]
+    fn normalization() {
+        let decomposed = "a\u{308}";
+        let mut de = Equivalence::default();
+        assert_eq!(de.quality("ä", decomposed), Match::Exact);
+        assert_eq!(de.quality(decomposed, "ä"), Match::Exact);
+        assert_eq!(de.quality("a", decomposed), Match::DiacriticLenient);
+        assert_eq!(
+            de.transliterate("ae", decomposed),
+            Transliteration::Complete
+        );
+        de.normalization = Normalization::Nfd;
+        assert_eq!(de.normalize("ä"), decomposed);
+        assert_eq!(de.quality("ä", decomposed), Match::Exact);
+        assert!(Equivalence::from_json(r#"{"language": "de", "normalization": "nfd"}"#).is_ok());
+    }
+}
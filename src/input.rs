@@ -0,0 +1,144 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+// the hidden input always holds this value so that soft keyboards have something to delete
+pub const SENTINEL: &str = "_";
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum InputAction {
+    Type(String),
+    Backspace,
+    DeleteWord,
+    NextWord,
+}
+
+impl InputAction {
+    pub fn from_key(key: &str, ctrl: bool, alt: bool, meta: bool) -> Option<Self> {
+        match key {
+            "Backspace" if ctrl || alt => Some(InputAction::DeleteWord),
+            "Backspace" => Some(InputAction::Backspace),
+            " " | "Spacebar" if !ctrl && !meta => Some(InputAction::NextWord),
+            // AltGr is reported as ctrl + alt on Windows, so only plain ctrl is a shortcut
+            _ if meta || (ctrl && !alt) => None,
+            _ => {
+                let mut graphemes = key.graphemes(true);
+                match (graphemes.next(), graphemes.next()) {
+                    (Some(grapheme), None) if !grapheme.chars().any(char::is_control) => {
+                        Some(InputAction::Type(grapheme.to_string()))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    pub fn from_text(text: &str) -> Vec<Self> {
+        text.graphemes(true)
+            .filter_map(|grapheme| match grapheme {
+                " " => Some(InputAction::NextWord),
+                grapheme
+                    if grapheme
+                        .chars()
+                        .any(|c| c.is_whitespace() || c.is_control()) =>
+                {
+                    None
+                }
+                grapheme => Some(InputAction::Type(grapheme.to_string())),
+            })
+            .collect()
+    }
+
+    pub fn from_input(input_type: &str, data: Option<&str>) -> Vec<Self> {
+        match input_type {
+            "insertText" => data.map(Self::from_text).unwrap_or_default(),
+            "deleteContentBackward" => vec![InputAction::Backspace],
+            "deleteWordBackward" | "deleteSoftLineBackward" | "deleteHardLineBackward" => {
+                vec![InputAction::DeleteWord]
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn from_value(value: &str) -> Vec<Self> {
+        match value.strip_prefix(SENTINEL) {
+            Some(inserted) => Self::from_text(inserted),
+            None => vec![InputAction::Backspace],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printable_keys_are_typed() {
+        "ÄäÖöÜüß0123456789!?\"'()„“–-.,:;".chars().for_each(|c| {
+            assert_eq!(
+                InputAction::from_key(&c.to_string(), false, false, false),
+                Some(InputAction::Type(c.to_string()))
+            )
+        });
+    }
+
+    #[test]
+    fn altgr_keys_are_typed() {
+        assert_eq!(
+            InputAction::from_key("@", true, true, false),
+            Some(InputAction::Type("@".to_string()))
+        );
+    }
+
+    #[test]
+    fn named_and_shortcut_keys() {
+        assert_eq!(
+            InputAction::from_key(" ", false, false, false),
+            Some(InputAction::NextWord)
+        );
+        assert_eq!(
+            InputAction::from_key("Backspace", false, false, false),
+            Some(InputAction::Backspace)
+        );
+        assert_eq!(
+            InputAction::from_key("Backspace", true, false, false),
+            Some(InputAction::DeleteWord)
+        );
+        assert_eq!(InputAction::from_key("Shift", false, false, false), None);
+        assert_eq!(InputAction::from_key("Dead", false, false, false), None);
+        assert_eq!(InputAction::from_key("c", true, false, false), None);
+        assert_eq!(InputAction::from_key("v", false, false, true), None);
+    }
+
+    #[test]
+    fn input_events() {
+        assert_eq!(
+            InputAction::from_input("insertText", Some("ß ")),
+            vec![InputAction::Type("ß".to_string()), InputAction::NextWord]
+        );
+        assert_eq!(
+            InputAction::from_input("deleteContentBackward", None),
+            vec![InputAction::Backspace]
+        );
+        assert_eq!(
+            InputAction::from_input("deleteWordBackward", None),
+            vec![InputAction::DeleteWord]
+        );
+        assert_eq!(
+            InputAction::from_input("insertFromPaste", Some("abc")),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn value_fallback() {
+        assert_eq!(InputAction::from_value(SENTINEL), vec![]);
+        assert_eq!(InputAction::from_value(""), vec![InputAction::Backspace]);
+        assert_eq!(
+            InputAction::from_value("_ü"),
+            vec![InputAction::Type("ü".to_string())]
+        );
+        assert_eq!(
+            InputAction::from_value("_u\u{308}"),
+            vec![InputAction::Type("u\u{308}".to_string())]
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::segmenter::Segmenter;
+
+pub const TARGETS: [&str; 6] = ["en", "de", "fr", "es", "pl", "nl"];
+
+// a text is never translated into its own language
+pub fn targets(source: &str) -> impl Iterator<Item = &'static str> + '_ {
+    TARGETS.into_iter().filter(move |target| *target != source)
+}
+
+pub fn language_name(code: &str) -> &str {
+    match code {
+        "de" => "German",
+        "en" => "English",
+        "fr" => "French",
+        "es" => "Spanish",
+        "pl" => "Polish",
+        "nl" => "Dutch",
+        code => code,
+    }
+}
+
+// iso 639-1 codes of the practiced text and of its translation
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LanguagePair {
+    pub source: String,
+    pub target: String,
+}
+
+impl Default for LanguagePair {
+    fn default() -> Self {
+        LanguagePair {
+            source: "de".to_string(),
+            target: "en".to_string(),
+        }
+    }
+}
+
+impl LanguagePair {
+    // there is no model that translates a language into itself
+    pub fn new(source: &str, target: &str) -> Option<Self> {
+        (source != target).then(|| LanguagePair {
+            source: source.to_string(),
+            target: target.to_string(),
+        })
+    }
+
+    // keeps the target unless it is the source itself
+    pub fn for_source(source: &str, target: &str) -> Self {
+        Self::new(source, target).unwrap_or_else(|| LanguagePair {
+            source: source.to_string(),
+            target: targets(source).next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariantKind {
+    Machine,
+    Literal,
+    Idiomatic,
+    #[serde(rename = "own")]
+    User,
+}
+
+impl VariantKind {
+    pub const ALL: [VariantKind; 4] = [
+        VariantKind::Machine,
+        VariantKind::Literal,
+        VariantKind::Idiomatic,
+        VariantKind::User,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantKind::Machine => "machine",
+            VariantKind::Literal => "literal",
+            VariantKind::Idiomatic => "idiomatic",
+            VariantKind::User => "own",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+// word indices of the sentance and of the translation that belong together, the
+// words of one side need not be adjacent and may be part of other alignments too.
+// both sets are ordered so equality, ordering and hashing all use the full sets,
+// and alignments sort by their first word of the sentance
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct Alignment {
+    pub original: BTreeSet<usize>,
+    pub translation: BTreeSet<usize>,
+}
+
+impl Alignment {
+    // both sides need at least one word
+    pub fn new(original: BTreeSet<usize>, translation: BTreeSet<usize>) -> Option<Self> {
+        (!original.is_empty() && !translation.is_empty()).then_some(Alignment {
+            original,
+            translation,
+        })
+    }
+}
+
+// alignments refer to the words of this variant, every variant has its own
+#[derive(Clone, PartialEq, Debug)]
+pub struct Variant {
+    pub kind: VariantKind,
+    pub text: Rc<str>,
+    pub alignments: Vec<Alignment>,
+}
+
+// machine variants are kept in n-best order, the first one is preferred until the learner
+// picks another
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Translations {
+    pub variants: Vec<Variant>,
+    pub preferred: usize,
+}
+
+impl Translations {
+    pub fn machine(texts: impl IntoIterator<Item = String>) -> Self {
+        let mut translations = Translations::default();
+        texts.into_iter().for_each(|text| {
+            translations.add(VariantKind::Machine, &text);
+        });
+        translations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    pub fn preferred(&self) -> Option<&Variant> {
+        self.variants
+            .get(self.preferred)
+            .or_else(|| self.variants.first())
+    }
+
+    // returns the index of the variant, an existing variant with the same text is reused
+    pub fn add(&mut self, kind: VariantKind, text: &str) -> Option<usize> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        if let Some(index) = self
+            .variants
+            .iter()
+            .position(|variant| &*variant.text == text)
+        {
+            return Some(index);
+        }
+        self.variants.push(Variant {
+            kind,
+            text: text.into(),
+            alignments: Vec::new(),
+        });
+        Some(self.variants.len() - 1)
+    }
+
+    pub fn prefer(&mut self, index: usize) {
+        if index < self.variants.len() {
+            self.preferred = index;
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Entry {
+    pub text: Rc<str>,
+    pub translations: Translations,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Lesson {
+    pub title: String,
+    pub pair: LanguagePair,
+    pub sentances: Vec<Entry>,
+}
+
+impl Lesson {
+    // sentances start without a translation, see queue::pending
+    pub fn from_text(text: &str, pair: LanguagePair) -> Self {
+        Lesson {
+            sentances: Segmenter::builtin(&pair.source)
+                .segment(text)
+                .into_iter()
+                .map(|sentance| Entry {
+                    text: sentance.into(),
+                    translations: Translations::default(),
+                })
+                .collect(),
+            pair,
+            ..Lesson::default()
+        }
+    }
+
+    // the text and its language stay, translations into another language are requested
+    // again, see queue::pending
+    pub fn with_target(&self, target: &str) -> Self {
+        match LanguagePair::new(&self.pair.source, target) {
+            Some(pair) if pair != self.pair => Lesson {
+                title: self.title.clone(),
+                pair,
+                sentances: self
+                    .sentances
+                    .iter()
+                    .map(|entry| Entry {
+                        text: entry.text.clone(),
+                        translations: Translations::default(),
+                    })
+                    .collect(),
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lessons_are_segmented_per_source_language() {
+        let text = "Gas usw. Dann kam er. Gut.";
+        let german = Lesson::from_text(text, LanguagePair::default());
+        assert_eq!(german.sentances.len(), 2);
+        let dutch = Lesson::from_text(text, LanguagePair::new("nl", "en").unwrap());
+        assert_eq!(dutch.sentances.len(), 3);
+        assert_eq!(dutch.pair.source, "nl");
+        assert!(dutch
+            .sentances
+            .iter()
+            .all(|entry| entry.translations.is_empty()));
+    }
+
+    #[test]
+    fn pairs_need_two_languages() {
+        assert_eq!(LanguagePair::new("de", "de"), None);
+        assert!(!targets("de").any(|target| target == "de"));
+        assert_eq!(targets("en").next(), Some("de"));
+
+        assert_eq!(
+            LanguagePair::for_source("en", "en"),
+            LanguagePair::new("en", "de").unwrap()
+        );
+        assert_eq!(
+            LanguagePair::for_source("fr", "en"),
+            LanguagePair::new("fr", "en").unwrap()
+        );
+
+        let mut german = Lesson::from_text("Gas usw. Dann kam er. Gut.", LanguagePair::default());
+        german.title = "Gas".to_string();
+        german.sentances[1].translations = Translations::machine(["Good.".to_string()]);
+        assert_eq!(german.with_target("en"), german);
+        assert_eq!(german.with_target("de"), german);
+        let french = german.with_target("fr");
+        assert_eq!(french.title, "Gas");
+        assert_eq!(french.pair, LanguagePair::new("de", "fr").unwrap());
+        assert_eq!(french.sentances.len(), 2);
+        assert!(french
+            .sentances
+            .iter()
+            .all(|entry| entry.translations.is_empty()));
+    }
+
+    #[test]
+    fn variants() {
+        let mut translations =
+            Translations::machine(["even earn what".to_string(), "even earn some".to_string()]);
+        assert_eq!(&*translations.preferred().unwrap().text, "even earn what");
+        assert_eq!(
+            translations.add(VariantKind::User, " even earn some "),
+            Some(1)
+        );
+        assert_eq!(translations.add(VariantKind::User, " "), None);
+        let own = translations
+            .add(VariantKind::Idiomatic, "and even make money")
+            .unwrap();
+        translations.prefer(own);
+        let preferred = translations.preferred().unwrap();
+        assert_eq!(preferred.kind, VariantKind::Idiomatic);
+        translations.prefer(10);
+        assert_eq!(translations.preferred, own);
+        assert_eq!(Translations::default().preferred(), None);
+    }
+
+    fn alignment(original: &[usize], translation: &[usize]) -> Alignment {
+        Alignment::new(
+            original.iter().copied().collect(),
+            translation.iter().copied().collect(),
+        )
+        .unwrap()
+    }
+
+    fn hash(alignment: &Alignment) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        alignment.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn alignments_starting_at_the_same_word_coexist() {
+        let alignments: BTreeSet<Alignment> = [
+            alignment(&[0], &[0]),
+            alignment(&[0, 1], &[0]),
+            alignment(&[0], &[1]),
+            alignment(&[0], &[0]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(alignments.len(), 3);
+        let hashed: std::collections::HashSet<Alignment> = alignments.iter().cloned().collect();
+        assert_eq!(hashed.len(), 3);
+        assert_eq!(Alignment::new(BTreeSet::new(), [0].into()), None);
+    }
+
+    #[test]
+    fn discontinuous_alignments_have_a_stable_identity() {
+        // "rief ... an" and "called"
+        let split = alignment(&[1, 4], &[1]);
+        let reordered = Alignment::new([4, 1].into(), [1].into()).unwrap();
+        assert_eq!(split, reordered);
+        assert_eq!(split.cmp(&reordered), std::cmp::Ordering::Equal);
+        assert_eq!(hash(&split), hash(&reordered));
+        let sorted: Vec<Alignment> = [
+            alignment(&[2], &[0, 3]),
+            split.clone(),
+            alignment(&[1], &[1]),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+        assert_eq!(
+            sorted,
+            vec![alignment(&[1], &[1]), split, alignment(&[2], &[0, 3])]
+        );
+        assert_ne!(hash(&sorted[0]), hash(&sorted[1]));
+    }
+}
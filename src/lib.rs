@@ -1,4 +1,17 @@
+pub mod alignments;
+pub mod bundle;
+pub mod cache;
 pub mod components;
+pub mod equivalence;
+mod input;
+pub mod lesson;
 pub mod popup;
-mod types;
-mod utils;
+pub mod progress;
+pub mod provider;
+pub mod queue;
+pub mod segmenter;
+pub mod settings;
+pub mod store;
+pub mod tokenizer;
+pub mod translate;
+pub mod types;
@@ -1,5 +1,21 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use leptos::*;
-use typing::{components::Sentance, popup::Popup};
+use typing::{
+    alignments::AlignmentStore,
+    bundle,
+    cache::{CacheStorage, TranslationCache},
+    components::Sentance,
+    equivalence::LANGUAGES,
+    lesson::{language_name, Entry, LanguagePair, Lesson, Translations},
+    popup::Popup,
+    progress::{ProgressStore, SavedProgress},
+    queue::{StatusIndicator, TranslationQueue},
+    settings::{Settings, SettingsPanel},
+    store::LessonStore,
+    types::TypeResults,
+};
 fn main() {
     console_error_panic_hook::set_once();
     mount_to_body(|| view! { <App/> })
@@ -8,7 +24,12 @@ fn main() {
 #[component]
 fn App() -> impl IntoView {
     let (translation_input, set_translation_input) = create_signal("".to_string());
-    let sentances = vec![
+    let settings = create_rw_signal(Settings::default());
+    provide_context(settings);
+    let progress = SavedProgress::new(ProgressStore::open(CacheStorage::named("progress")));
+    provide_context(progress);
+    let _ = window_event_listener(ev::pagehide, move |_| progress.flush());
+    let sentances = [
         "Mit intelligenten Stromzählern können Verbraucher selbst am Energiemarkt teilnehmen. Wie Sie Geld sparen und sogar welches verdienen.",
         "Die Preise an der Strombörse fahren an vielen Tagen des Jahres Achterbahn: Sie vervielfachen sich oft binnen weniger Stunden, um kurz darauf genauso rasant wieder abzustürzen. Mitunter gar in den negativen Bereich – die Versorger bekommen dann Geld dafür, dass sie Strom abnehmen.",
         "Für die Verbraucher hat dieses Auf und Ab keine unmittelbaren Folgen, da sie für ihren Strom in der Regel stets den gleichen Preis zahlen. Damit gewinnen sie Sicherheit. Das bedeutet aber auch, dass sie nichts davon haben, wenn es an der Börse mal wieder abwärtsgeht.",
@@ -23,17 +44,122 @@ fn App() -> impl IntoView {
 
     ];
 
-    let (popup, set_popup) = create_signal(None);
+    let open_lesson = CacheStorage::named("lesson");
+    let lesson = create_rw_signal(bundle::restore(&open_lesson).unwrap_or_else(|| {
+        Lesson {
+            sentances: sentances
+                .into_iter()
+                .zip(translations)
+                .map(|(text, translation)| Entry {
+                    text: Rc::from(text),
+                    translations: Translations::machine([translation.to_string()]),
+                })
+                .collect(),
+            ..Lesson::default()
+        }
+    }));
+    create_effect(move |_| lesson.with(|lesson| bundle::keep(&open_lesson, lesson)));
+    let cache = store_value(Rc::new(RefCell::new(TranslationCache::open(
+        CacheStorage::default(),
+    ))));
+    let queue = TranslationQueue::new(lesson, settings, cache);
+    let store = LessonStore::new(
+        lesson,
+        AlignmentStore::open(CacheStorage::named("alignments")),
+    );
+    // the language of pasted text, it becomes the source of the lesson
+    let (text_language, set_text_language) =
+        create_signal(settings.with_untracked(|settings| settings.pair.source.clone()));
+    let load_lesson = move |text: &str| {
+        let loaded = Lesson::from_text(
+            text,
+            LanguagePair::for_source(
+                &text_language.get_untracked(),
+                &settings.with_untracked(|settings| settings.pair.target.clone()),
+            ),
+        );
+        if loaded.sentances.is_empty() {
+            return;
+        }
+        lesson.set(loaded);
+        queue.start();
+    };
+
+    create_effect(move |_| {
+        let texts: Vec<Rc<str>> = store
+            .sentances
+            .with(|sentances| sentances.iter().map(|(text, _)| text.clone()).collect());
+        progress.prune(&texts);
+    });
+
+    // the rules follow the language of the lesson
+    create_effect(move |_| {
+        let pair = lesson.with(|lesson| lesson.pair.clone());
+        if settings.with_untracked(|settings| settings.pair != pair) {
+            settings.update(|settings| settings.set_pair(&pair));
+        }
+    });
+    // a target picked in the settings only asks for new translations
+    create_effect(move |_| {
+        let target = settings.with(|settings| settings.pair.target.clone());
+        if lesson.with_untracked(|lesson| lesson.pair.target != target) {
+            lesson.update(|lesson| *lesson = lesson.with_target(&target));
+            queue.start();
+        }
+    });
+
+    let (import_error, set_import_error) = create_signal(None::<String>);
+    let import_lesson = move |json: &str| match bundle::load(json) {
+        Ok(loaded) => {
+            set_import_error(None);
+            store.load(loaded);
+            queue.start();
+            true
+        }
+        Err(error) => {
+            set_import_error(Some(error.to_string()));
+            false
+        }
+    };
+
+    let load_rules = move |json: &str| {
+        let mut updated = settings.get_untracked();
+        match updated.set_custom_rules(json) {
+            Ok(()) => {
+                settings.set(updated);
+                set_import_error(None);
+                true
+            }
+            Err(error) => {
+                set_import_error(Some(error));
+                false
+            }
+        }
+    };
+
+    // the index of the sentance and the translation it showed
+    let (popup, set_popup) = create_signal(None::<(usize, Rc<str>)>);
 
     let popup_component = move || {
-        if let Some((t, tr)) = popup() {
+        let opened = popup().and_then(|(index, translation)| {
+            let (text, state) = store
+                .sentances
+                .with(|sentances| sentances.get(index).cloned())?;
+            Some((text, state, translation))
+        });
+        if let Some((text, state, tr)) = opened {
+            let saved = store.alignments(&text, &tr);
+            let on_alignments = Callback::new({
+                let tr = tr.clone();
+                move |changed| store.set_alignments(&text, &tr, changed)
+            });
             view! {
                 <div class="fixed inset-0 bg-gray-500 bg-opacity-75 transition-opacity">
                     <div class="fixed inset-0 z-10 w-screen overflow-y-auto">
                         <div class="flex min-h-full items-end justify-center p-4 text-center sm:items-center sm:p-0 lg:p-5">
                             <div class="flex relative transform overflow-hidden bg-gray-100 shadow-xl transition-all w-full h-full">
                                 <div class="bg-white px-4 pb-4 pt-5 sm:p-6 sm:pb-4">
-                                    <Popup text=t translation=tr display=None/>
+                                    <Popup state=state translation=tr alignments=saved on_alignments=on_alignments/>
                                     <input
                                         type="button"
                                         value="Close"
@@ -70,14 +196,62 @@ fn App() -> impl IntoView {
                                         }
                                     >
                                     </textarea>
+                                    <select on:change=move |event| set_text_language(event_target_value(&event))>
+                                        {LANGUAGES
+                                            .into_iter()
+                                            .map(|language| {
+                                                view! {
+                                                    <option
+                                                        value=language
+                                                        selected=move || text_language.with(|text_language| text_language == language)
+                                                    >
+                                                        {language_name(language)}
+                                                    </option>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </select>
                                     <input
                                         type="button"
-                                        value="Close"
+                                        value="Load"
                                         on:click=move |_event| {
-                                            logging::log!("{}", translation_input.get());
+                                            translation_input.with(|text| load_lesson(text));
+                                            set_popup.set(None);
                                             set_input_popup.set(false);
                                         }
                                     />
+                                    <input
+                                        type="button"
+                                        value="Import lesson file"
+                                        on:click=move |_event| {
+                                            if translation_input.with(|json| import_lesson(json)) {
+                                                set_popup.set(None);
+                                                set_input_popup.set(false);
+                                            }
+                                        }
+                                    />
+                                    <input
+                                        type="button"
+                                        value="Load typing rules"
+                                        on:click=move |_event| {
+                                            if translation_input.with(|json| load_rules(json)) {
+                                                set_input_popup.set(false);
+                                            }
+                                        }
+                                    />
+                                    <input
+                                        type="button"
+                                        value="Export lesson file"
+                                        on:click=move |_event| {
+                                            set_translation_input(bundle::save(&store.export()))
+                                        }
+                                    />
+                                    <div class="text-red-600">{import_error}</div>
+                                    <input
+                                        type="button"
+                                        value="Close"
+                                        on:click=move |_event| set_input_popup.set(false)
+                                    />
 
                                 </div>
                             </div>
@@ -90,17 +264,62 @@ fn App() -> impl IntoView {
         }
     };
 
-    let views = sentances
-        .iter()
-        .zip(translations)
-        .map(|(item, translation)| {
-            view! { <Sentance text=item translation=translation display=Some(set_popup)/> }
-        })
-        .collect_view();
+    let views = move || {
+        let sentances = store.sentances.get();
+        let node_refs: Vec<NodeRef<html::Div>> =
+            sentances.iter().map(|_| create_node_ref()).collect();
+        sentances
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_text, state))| {
+                let next = node_refs.get(index + 1).copied();
+                let on_complete = Callback::new(move |_: TypeResults| {
+                    if let Some(next) = next.and_then(|next| next.get()) {
+                        next.scroll_into_view();
+                        let _ = next.focus();
+                    }
+                });
+                let on_translations = Callback::new(move |translations: Translations| {
+                    store.set_translations(index, translations)
+                });
+                let display =
+                    Callback::new(move |translation| set_popup(Some((index, translation))));
+                view! {
+                    <Sentance
+                        state=state
+                        translations=store.translations(index)
+                        display=Some(display)
+                        on_complete=on_complete
+                        on_translations=on_translations
+                        node_ref=node_refs[index]
+                    />
+                }
+            })
+            .collect_view()
+    };
 
     view! {
         <div class="p-3 pt-7 lg:text-3xl text-5xl font-bold text-gray-100 font-mono w-screen items-center flex flex-col snap-start">
+            <div>
+                {move || {
+                    lesson.with(|lesson| format!("Learn {} by typing!", language_name(&lesson.pair.source)))
+                }}
+            </div>
             <div on:click=move |_event| set_input_popup(true)>Update text!</div>
+            <SettingsPanel settings=settings/>
+            <StatusIndicator status=queue.status/>
+            <input
+                class="text-xl lg:text-base font-normal text-gray-700"
+                type="button"
+                value="Clear translation cache"
+                on:click=move |_event| cache.with_value(|cache| cache.borrow_mut().invalidate(None))
+            />
+            <input
+                class="text-xl lg:text-base font-normal text-gray-700"
+                type="button"
+                value="Reset lesson progress"
+                on:click=move |_event| progress.reset_all()
+            />
         </div>
         <div class="w-screen flex flex-col items-center">
             <div>{move || input_popup_component}</div>
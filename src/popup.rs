@@ -1,45 +1,13 @@
 use std::collections::BTreeSet;
-use std::collections::HashSet;
-use std::hash::Hash;
+use std::rc::Rc;
 
 use leptos::*;
 
+use crate::components::{focus_input, pending_at, TypingInput};
+use crate::lesson::Alignment;
+use crate::tokenizer::{tokenize, TokenKind};
 use crate::types::TypeState;
-use crate::utils::compare;
-use core::hash::Hasher;
 
-#[derive(Eq, PartialEq, Clone, Debug)]
-struct Association {
-    start_position: usize,
-    original: BTreeSet<usize>,
-    translation: HashSet<usize>,
-}
-
-impl Association {
-    fn new(original: BTreeSet<usize>, translation: HashSet<usize>) -> Self {
-        Association {
-            start_position: *original.iter().next().unwrap(),
-            original,
-            translation,
-        }
-    }
-}
-impl PartialOrd for Association {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.start_position.partial_cmp(&other.start_position)
-    }
-}
-impl Ord for Association {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.start_position.cmp(&other.start_position)
-    }
-}
-impl Hash for Association {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.original.iter().for_each(|item| item.hash(state));
-        self.translation.iter().for_each(|item| item.hash(state));
-    }
-}
 #[derive(Clone)]
 enum Clicked {
     Original(usize),
@@ -54,16 +22,26 @@ enum ClickedHeighlight {
 }
 #[component]
 pub fn Popup(
-    text: &'static str,
-    translation: &'static str,
-    display: Option<WriteSignal<Option<(&'static str, &'static str)>>>,
+    state: RwSignal<TypeState>,
+    #[prop(into)] translation: Rc<str>,
+    #[prop(optional)] alignments: Vec<Alignment>,
+    #[prop(optional)] on_alignments: Option<Callback<Vec<Alignment>>>,
 ) -> impl IntoView {
-    let (store, set_store) = create_signal(TypeState::from_str(text));
+    let (store, set_store) = (state.read_only(), state.write_only());
+    let input_ref = create_node_ref::<html::Input>();
     let (pair, set_pair) = create_signal(false);
     let (original_selected, set_original_selected) = create_signal(BTreeSet::<usize>::new());
-    let (translation_selected, set_translation_selected) = create_signal(HashSet::<usize>::new());
+    let (translation_selected, set_translation_selected) = create_signal(BTreeSet::<usize>::new());
 
-    let (pairs, set_pairs) = create_signal(BTreeSet::<Association>::new());
+    let (pairs, set_pairs) = create_signal(
+        alignments
+            .into_iter()
+            .filter_map(|alignment| Alignment::new(alignment.original, alignment.translation))
+            .collect::<BTreeSet<_>>(),
+    );
+    if let Some(on_alignments) = on_alignments {
+        create_effect(move |_| on_alignments(pairs.with(|pairs| pairs.iter().cloned().collect())));
+    }
 
     let (clicked, set_clicked) = create_signal(Clicked::None);
     let (clicked_highlight, set_clicked_highlight) = create_signal(ClickedHeighlight::None);
@@ -78,15 +56,11 @@ pub fn Popup(
                             logging::log!("current pairs {:?}", pairs.get_untracked());
                             set_pairs
                                 .update(|item| {
-                                    if original_selected.get_untracked().len() > 0
-                                        && translation_selected.get_untracked().len() > 0
-                                    {
-                                        item.insert(
-                                            Association::new(
-                                                original_selected.get_untracked(),
-                                                translation_selected.get_untracked(),
-                                            ),
-                                        );
+                                    if let Some(alignment) = Alignment::new(
+                                        original_selected.get_untracked(),
+                                        translation_selected.get_untracked(),
+                                    ) {
+                                        item.insert(alignment);
                                     }
                                 });
                             set_original_selected
@@ -136,8 +110,8 @@ pub fn Popup(
             .into_view()
     };
     let update_pair = move || {
-        if original_selected.get_untracked().len() > 0
-            && translation_selected.get_untracked().len() > 0
+        if !original_selected.get_untracked().is_empty()
+            && !translation_selected.get_untracked().is_empty()
         {
             set_pair.set(true);
         } else {
@@ -174,76 +148,31 @@ pub fn Popup(
             .find(|(_pair_index, item)| item.translation.iter().any(|item| *item == index))
             .map_or_else(|| None, |(pair_index, _item)| Some(pair_index))
     };
-    let translation_words: Vec<&str> = translation.split(" ").collect();
+    // punctuation is shown between the translation words but cannot be aligned
+    let translation_tokens: Vec<(Option<usize>, String)> = tokenize(&translation)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .scan(0, |words, token| {
+            let index = token.is_word().then(|| {
+                *words += 1;
+                *words - 1
+            });
+            Some((index, token.text.to_string()))
+        })
+        .collect();
     view! {
         <div
-            on:click=move |_| {
-                if let Some(action) = display {
-                    action(Some((text, translation)))
-                }
-            }
-
-            class="p-3 flex flex-wrap text-4xl lg:text-3xl text-gray-500 focus:bg-gray-300 font-mono"
+            class="relative p-3 flex flex-wrap text-4xl lg:text-3xl text-gray-500 focus-within:bg-gray-300 font-mono"
             tabindex=1
-            on:keydown=move |event| {
-                let key = event.key_code();
-                let mut local_store = store.get();
-                logging::log!("key down {}", key);
-                let word = local_store.data.get_mut(local_store.word_index).unwrap();
-                if key == 8 {
-                    if word.char_index > 0 {
-                        word.char_index -= 1;
-                        let temp = word.data.get_mut(word.char_index).unwrap();
-                        temp.backspace();
-                    } else if local_store.word_index > 0 {
-                        local_store.word_index -= 1;
-                    }
-                    set_store(local_store);
-                } else if (key == 32) && local_store.word_index < local_store.data.len() {
-                    event.prevent_default();
-                    local_store.word_index += 1;
-                    set_store(local_store);
-                }
-            }
-
-            on:focus=move |_event| { set_store.update(|store| store.focus = true) }
-
-            on:focusout=move |_event| { set_store.update(|store| store.focus = false) }
-
-            on:keypress=move |event| {
-                let key = event.key_code();
-                match key {
-                    (64..=93) | (97..=122) | 44 | 45 | 46 | 58 | 59 => {
-                        let mut local_store = store.get();
-                        if local_store.word_index < local_store.data.len() {
-                            logging::log!("current index {}", local_store.word_index);
-                            logging::log!(
-                                "current word index {}", local_store.data.get(local_store
-                                .word_index).unwrap().char_index
-                            );
-                            let word = local_store.data.get_mut(local_store.word_index).unwrap();
-                            if word.char_index < word.data.len() {
-                                logging::log!("inserting {}", char::from_u32(key).unwrap());
-                                word.data
-                                    .get_mut(word.char_index)
-                                    .unwrap()
-                                    .typed(char::from_u32(key).unwrap());
-                                word.char_index += 1;
-                                set_store(local_store);
-                            }
-                        }
-                    }
-                    _ => {}
-                };
-                logging::log!("keypress {:?}", & key);
-            }
+            on:focus=move |_event| focus_input(input_ref)
         >
+            <TypingInput store=store set_store=set_store on_complete=None node_ref=input_ref/>
 
             {
                 view! {
                     <For
                         each=move || store.get().data.into_iter().enumerate()
-                        key=move |(index, c)| { format!("{}-{}", index, c.char_index) }
+                        key=move |(index, c)| { format!("{}-{}", index, c.key()) }
 
                         children=move |(word_index, c)| {
                             let class = move || {
@@ -338,12 +267,28 @@ pub fn Popup(
                                     <For
                                         each=move || c.clone().data.into_iter().enumerate()
                                         key=|(index, c)| {
-                                            format!("{}-{}", index, c.typed_char.unwrap_or('~'))
+                                            format!("{}-{}-{:?}", index, c.typed_text(), c.quality)
                                         }
 
-                                        children=move |(_index, c)| {
-                                            if let Some(typed_char) = c.typed_char {
-                                                if compare(typed_char, c.reference_char) {
+                                        children=move |(index, c)| {
+                                            if c.typed.is_some() {
+                                                if c.is_lenient() {
+                                                    let class = move || {
+                                                        if store.get().word_index == word_index {
+                                                            "relative min-w-4 text-amber-700 underline"
+                                                        } else {
+                                                            "relative min-w-4 text-amber-700"
+                                                        }
+                                                    };
+                                                    return view! {
+                                                        <div class=class>
+                                                            {c.reference.clone()}
+                                                            <div class="absolute -top-0 -right-0 text-amber-500 italic text-base md:text-3xl">
+                                                                <p>{c.typed_text()}</p>
+                                                            </div>
+                                                        </div>
+                                                    };
+                                                } else if c.is_correct() {
                                                     let class = move || {
                                                         if store.get().word_index == word_index {
                                                             "min-w-4 text-gray-900 underline"
@@ -351,13 +296,13 @@ pub fn Popup(
                                                             "min-w-4 text-gray-900"
                                                         }
                                                     };
-                                                    return view! { <div class=class>{c.reference_char}</div> };
+                                                    return view! { <div class=class>{c.reference.clone()}</div> };
                                                 } else {
                                                     return view! {
                                                         <div class="relative text-gray-400 min-w-4 underline">
-                                                            {c.reference_char}
+                                                            {c.reference.clone()}
                                                             <div class="absolute -top-0 -right-0 text-red-600 italic text-base md:text-3xl">
-                                                                <p>{c.typed_char}</p>
+                                                                <p>{c.typed_text()}</p>
                                                             </div>
                                                         </div>
                                                     };
@@ -366,12 +311,17 @@ pub fn Popup(
                                             let class = move || {
                                                 if store.get().word_index == word_index && store.get().focus
                                                 {
-                                                    "min-w-4 underline"
+                                                    "relative min-w-4 underline"
                                                 } else {
-                                                    "min-w-4"
+                                                    "relative min-w-4"
                                                 }
                                             };
-                                            view! { <div class=class>{c.reference_char}</div> }
+                                            view! {
+                                                <div class=class>
+                                                    {c.reference.clone()}
+                                                    {pending_at(store, word_index, index)}
+                                                </div>
+                                            }
                                         }
                                     />
 
@@ -380,7 +330,7 @@ pub fn Popup(
                                         match clicked.get() {
                                             Clicked::Original(clicked_index) => {
                                                 if pair() && clicked_index == word_index {
-                                                    return pair_button.into_view()
+                                                    pair_button.into_view()
                                                 } else {
                                                     view! {}.into_view()
                                                 }
@@ -412,9 +362,12 @@ pub fn Popup(
         </div>
         <div class="px-8 p-5 flex flex-wrap text-4xl lg:text-3xl text-gray-500 italic">
             <For
-                each=move || translation_words.clone().into_iter().enumerate()
-                key=move |&(index, _item)| index
-                children=move |(index, item)| {
+                each=move || translation_tokens.clone().into_iter().enumerate()
+                key=move |(position, _token)| *position
+                children=move |(_position, (index, word))| {
+                    let Some(index) = index else {
+                        return view! { <div class="py-1">{word}</div> }.into_view();
+                    };
                     let class = move || {
                         if !highlight_translation(index)
                             && translation_selected.get().contains(&index)
@@ -502,7 +455,7 @@ pub fn Popup(
                             }
                         >
 
-                            {item}
+                            {word}
                             {hightlight_index}
 
                             {move || {
@@ -533,6 +486,7 @@ pub fn Popup(
 
                         </div>
                     }
+                        .into_view()
                 }
             />
 
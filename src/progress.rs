@@ -0,0 +1,329 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use std::time::Duration;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{cache_key, CacheStorage, KeyedStore};
+use crate::types::{Timing, TypeState};
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct TypedChar {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transliteration: Option<String>,
+}
+
+// what was typed in one sentance, the match quality is scored again on restore so
+// changed settings apply to it
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    pub word_index: usize,
+    pub char_indices: Vec<usize>,
+    pub typed: Vec<Vec<TypedChar>>,
+    #[serde(default)]
+    pub timing: Timing,
+}
+
+impl Progress {
+    pub fn of(state: &TypeState) -> Self {
+        Progress {
+            word_index: state.word_index,
+            char_indices: state.data.iter().map(|word| word.char_index).collect(),
+            typed: state
+                .data
+                .iter()
+                .map(|word| {
+                    word.data
+                        .iter()
+                        .map(|c| TypedChar {
+                            typed: c.typed.clone(),
+                            transliteration: c.transliteration.clone(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            timing: state.timing,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.word_index == 0 && self.char_indices.iter().all(|index| *index == 0)
+    }
+
+    // progress saved for another version of the text is not restored
+    pub fn restore(&self, state: &mut TypeState) -> bool {
+        let fits = self.word_index <= state.data.len()
+            && self.char_indices.len() == state.data.len()
+            && self.typed.len() == state.data.len()
+            && state
+                .data
+                .iter()
+                .zip(self.char_indices.iter().zip(&self.typed))
+                .all(|(word, (index, typed))| {
+                    *index <= word.data.len() && typed.len() == word.data.len()
+                });
+        if !fits {
+            return false;
+        }
+        state.word_index = self.word_index;
+        // the time since the last keystroke is a break, not typing
+        state.timing = Timing {
+            last: None,
+            ..self.timing
+        };
+        state
+            .data
+            .iter_mut()
+            .zip(self.char_indices.iter().zip(&self.typed))
+            .for_each(|(word, (index, typed))| {
+                word.char_index = *index;
+                word.data.iter_mut().zip(typed).for_each(|(c, typed)| {
+                    c.backspace();
+                    match &typed.transliteration {
+                        Some(sequence) => c.transliterated(sequence.clone()),
+                        None => c.typed = typed.typed.clone(),
+                    }
+                });
+            });
+        state.set_rules(state.rules.clone());
+        true
+    }
+}
+
+// the place of a sentance in the lesson is part of the key, so repeated sentances keep
+// their own progress
+fn progress_key(index: usize, text: &str) -> String {
+    format!("{}:{}", index, cache_key(text))
+}
+
+// typing progress per sentance, saves are only written by flush
+#[derive(Debug)]
+pub struct ProgressStore {
+    entries: KeyedStore<Progress>,
+}
+
+impl ProgressStore {
+    pub fn open(storage: CacheStorage) -> Self {
+        ProgressStore {
+            entries: KeyedStore::open(storage),
+        }
+    }
+
+    pub fn get(&self, index: usize, text: &str) -> Option<&Progress> {
+        self.entries.get(&progress_key(index, text))
+    }
+
+    // untouched sentances are dropped instead of stored, returns whether anything changed
+    pub fn save(&mut self, index: usize, text: &str, progress: Progress) -> bool {
+        let progress = Some(progress).filter(|progress| !progress.is_empty());
+        self.entries.set(progress_key(index, text), progress)
+    }
+
+    pub fn flush(&self) {
+        self.entries.flush();
+    }
+
+    // drops the progress of sentances that are not in the lesson, returns whether any was
+    pub fn retain(&mut self, texts: &[Rc<str>]) -> bool {
+        let keys: BTreeSet<String> = texts
+            .iter()
+            .enumerate()
+            .map(|(index, text)| progress_key(index, text))
+            .collect();
+        let len = self.entries.len();
+        self.entries.retain(|key, _| keys.contains(key));
+        self.entries.len() != len
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// keystrokes in quick succession are written together
+const FLUSH_DELAY: Duration = Duration::from_secs(1);
+
+// provided as context, every sentance restores and saves its own progress
+#[derive(Clone, Copy)]
+pub struct SavedProgress {
+    store: StoredValue<Rc<RefCell<ProgressStore>>>,
+    resets: RwSignal<usize>,
+    scheduled: StoredValue<bool>,
+}
+
+impl SavedProgress {
+    pub fn new(store: ProgressStore) -> Self {
+        SavedProgress {
+            store: store_value(Rc::new(RefCell::new(store))),
+            resets: create_rw_signal(0),
+            scheduled: store_value(false),
+        }
+    }
+
+    fn save(self, index: usize, text: &str, progress: Progress) {
+        let changed = self
+            .store
+            .with_value(|store| store.borrow_mut().save(index, text, progress));
+        if changed && !self.scheduled.get_value() {
+            self.scheduled.set_value(true);
+            set_timeout(move || self.flush(), FLUSH_DELAY);
+        }
+    }
+
+    // the store only keeps the progress of the lesson that is open
+    pub fn prune(self, texts: &[Rc<str>]) {
+        self.store.with_value(|store| {
+            if store.borrow_mut().retain(texts) {
+                store.borrow().flush();
+            }
+        });
+    }
+
+    // also called when the page is left, so a pending save is not lost
+    pub fn flush(self) {
+        if self.scheduled.get_value() {
+            self.scheduled.set_value(false);
+            self.store.with_value(|store| store.borrow().flush());
+        }
+    }
+
+    // resets every sentance that is shown, which drops their saved progress
+    pub fn reset_all(self) {
+        self.resets.update(|resets| *resets += 1);
+    }
+}
+
+pub(crate) fn follow_progress(
+    index: usize,
+    text: Rc<str>,
+    store: ReadSignal<TypeState>,
+    set_store: WriteSignal<TypeState>,
+) {
+    let Some(saved) = use_context::<SavedProgress>() else {
+        return;
+    };
+    let restored = saved
+        .store
+        .with_value(|progress| progress.borrow().get(index, &text).cloned());
+    if let Some(restored) = restored {
+        set_store.update(|store| {
+            restored.restore(store);
+        });
+    }
+    create_effect(move |_| {
+        let progress = store.with(Progress::of);
+        saved.save(index, &text, progress);
+    });
+    create_effect(move |previous: Option<usize>| {
+        let resets = saved.resets.get();
+        if previous.is_some() {
+            set_store.update(TypeState::reset);
+        }
+        resets
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cache::TempFile;
+    use crate::equivalence::{Equivalence, Leniency};
+
+    #[test]
+    fn progress_is_restored() {
+        let mut state = TypeState::new("Straße ab");
+        "Strasse".chars().for_each(|c| {
+            state.type_char(c);
+        });
+        state.next_word();
+        state.type_char('x');
+        let progress = Progress::of(&state);
+
+        let mut restored = TypeState::new("Straße ab");
+        assert!(progress.restore(&mut restored));
+        assert_eq!(restored.cursor(), state.cursor());
+        assert_eq!(restored.results(), state.results());
+        assert_eq!(Progress::of(&restored), progress);
+        assert!(!progress.restore(&mut TypeState::new("Straße")));
+
+        restored.reset();
+        assert!(Progress::of(&restored).is_empty());
+        assert_eq!(restored.results().missing, 8);
+    }
+
+    #[test]
+    fn restore_scores_with_the_current_rules() {
+        let mut state = TypeState::new("Größe");
+        "Groß".chars().for_each(|c| {
+            state.type_char(c);
+        });
+        assert_eq!(state.results().lenient, 1);
+        let mut strict = TypeState::new("Größe");
+        strict.set_rules(Rc::new(Equivalence::default().with_level(Leniency::Strict)));
+        assert!(Progress::of(&state).restore(&mut strict));
+        assert_eq!(strict.results().lenient, 0);
+        assert_eq!(strict.results().wrong, 1);
+
+        let mut state = TypeState::new("Straße");
+        "Strasse".chars().for_each(|c| {
+            state.type_char(c);
+        });
+        let mut strict = TypeState::new("Straße");
+        strict.set_rules(Rc::new(Equivalence::default().with_level(Leniency::Strict)));
+        assert!(Progress::of(&state).restore(&mut strict));
+        assert_eq!(strict.results().wrong, 1);
+        let mut lenient = TypeState::new("Straße");
+        assert!(Progress::of(&strict).restore(&mut lenient));
+        assert_eq!(lenient.results().lenient, 1);
+        assert_eq!(lenient.results().wrong, 0);
+    }
+
+    #[test]
+    fn store_persists_and_drops_untouched_sentances() {
+        let file = TempFile::new("progress");
+        let storage = file.storage();
+        let mut state = TypeState::new("Gut so.");
+        state.type_char('G');
+
+        let mut store = ProgressStore::open(storage.clone());
+        assert!(store.save(0, "Gut so.", Progress::of(&state)));
+        assert!(!store.save(1, "Nein.", Progress::of(&TypeState::new("Nein."))));
+        assert_eq!(store.len(), 1);
+        assert!(ProgressStore::open(storage.clone()).is_empty());
+        store.flush();
+        let reopened = ProgressStore::open(storage.clone());
+        assert_eq!(reopened.get(0, " Gut  so."), Some(&Progress::of(&state)));
+        assert_eq!(reopened.get(2, "Gut so."), None);
+
+        state.reset();
+        store.save(0, "Gut so.", Progress::of(&state));
+        store.flush();
+        assert!(ProgressStore::open(storage).is_empty());
+    }
+
+    #[test]
+    fn repeated_sentances_keep_their_own_progress() {
+        let mut first = TypeState::new("Ja.");
+        first.type_char('J');
+        let mut store = ProgressStore::open(CacheStorage::Memory);
+        store.save(0, "Ja.", Progress::of(&first));
+        store.save(3, "Ja.", Progress::of(&TypeState::new("Ja.")));
+        assert_eq!(store.get(0, "Ja."), Some(&Progress::of(&first)));
+        assert_eq!(store.get(3, "Ja."), None);
+
+        store.save(1, "Nein.", Progress::of(&first));
+        assert!(store.retain(&[Rc::from("Ja."), Rc::from("Gut.")]));
+        assert_eq!(store.len(), 1);
+        assert!(store.get(0, "Ja.").is_some());
+        assert!(!store.retain(&[Rc::from("Ja.")]));
+    }
+}
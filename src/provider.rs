@@ -0,0 +1,139 @@
+use std::future::Future;
+
+use crate::lesson::LanguagePair;
+use crate::translate::{TranslateClient, TranslateError, Variants, MARIAN_MODELS};
+
+pub trait TranslationProvider {
+    fn name(&self) -> &'static str;
+    // the model that translates the pair, cached translations are kept per model
+    fn model(&self, pair: &LanguagePair) -> String;
+    // the variants of every sentance, in the same order
+    fn translate(
+        &self,
+        pair: &LanguagePair,
+        sentances: &[&str],
+    ) -> impl Future<Output = Result<Vec<Variants>, TranslateError>>;
+}
+
+impl TranslationProvider for TranslateClient {
+    fn name(&self) -> &'static str {
+        "marian"
+    }
+    fn model(&self, pair: &LanguagePair) -> String {
+        format!("{}-{}-{}", MARIAN_MODELS, pair.source, pair.target)
+    }
+    fn translate(
+        &self,
+        pair: &LanguagePair,
+        sentances: &[&str],
+    ) -> impl Future<Output = Result<Vec<Variants>, TranslateError>> {
+        TranslateClient::translate(self, pair, sentances)
+    }
+}
+
+// deterministic provider that works without a server, it "translates" a sentance to itself
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OfflineStub;
+
+impl TranslationProvider for OfflineStub {
+    fn name(&self) -> &'static str {
+        "offline"
+    }
+    fn model(&self, _pair: &LanguagePair) -> String {
+        "offline".to_string()
+    }
+    async fn translate(
+        &self,
+        _pair: &LanguagePair,
+        sentances: &[&str],
+    ) -> Result<Vec<Variants>, TranslateError> {
+        Ok(sentances
+            .iter()
+            .map(|sentance| vec![sentance.to_string()])
+            .collect())
+    }
+}
+
+// the providers that can be selected in the settings
+#[derive(Clone, PartialEq, Debug)]
+pub enum Translator {
+    Marian(TranslateClient),
+    Offline(OfflineStub),
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        Translator::Marian(TranslateClient::default())
+    }
+}
+
+impl Translator {
+    pub const NAMES: [&'static str; 2] = ["marian", "offline"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "marian" => Some(Translator::Marian(TranslateClient::default())),
+            "offline" => Some(Translator::Offline(OfflineStub)),
+            _ => None,
+        }
+    }
+}
+
+impl TranslationProvider for Translator {
+    fn name(&self) -> &'static str {
+        match self {
+            Translator::Marian(client) => client.name(),
+            Translator::Offline(stub) => stub.name(),
+        }
+    }
+    fn model(&self, pair: &LanguagePair) -> String {
+        match self {
+            Translator::Marian(client) => client.model(pair),
+            Translator::Offline(stub) => stub.model(pair),
+        }
+    }
+    async fn translate(
+        &self,
+        pair: &LanguagePair,
+        sentances: &[&str],
+    ) -> Result<Vec<Variants>, TranslateError> {
+        match self {
+            Translator::Marian(client) => client.translate(pair, sentances).await,
+            Translator::Offline(stub) => stub.translate(pair, sentances).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn offline_stub_is_deterministic() {
+        let translator = Translator::from_name("offline").unwrap();
+        let translated =
+            block_on(translator.translate(&LanguagePair::default(), &["Guten Tag.", "Danke!"]))
+                .unwrap();
+        assert_eq!(translated, vec![vec!["Guten Tag."], vec!["Danke!"]]);
+        assert_eq!(
+            block_on(translator.translate(&LanguagePair::default(), &["Guten Tag.", "Danke!"]))
+                .unwrap(),
+            translated
+        );
+    }
+
+    #[test]
+    fn names() {
+        Translator::NAMES.iter().for_each(|name| {
+            assert_eq!(Translator::from_name(name).unwrap().name(), *name);
+        });
+        assert_eq!(Translator::from_name("xx"), None);
+        assert_eq!(Translator::default().name(), "marian");
+        assert_eq!(
+            Translator::default().model(&LanguagePair::new("de", "fr").unwrap()),
+            "Helsinki-NLP/opus-mt-de-fr"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use leptos::*;
+
+use crate::cache::{CachedTranslator, TranslationCache};
+use crate::lesson::{Entry, Lesson, Translations};
+use crate::provider::TranslationProvider;
+use crate::settings::Settings;
+use crate::translate::Variants;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(2),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    // doubles with every attempt, starting at initial for the first retry
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max)
+            .min(self.max)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum TranslationStatus {
+    #[default]
+    Idle,
+    Translating(usize),
+    Offline {
+        attempt: u32,
+        retry_in: Duration,
+        error: String,
+    },
+    Failed(String),
+}
+
+impl TranslationStatus {
+    pub fn message(&self) -> String {
+        match self {
+            TranslationStatus::Idle => "translations up to date".to_string(),
+            TranslationStatus::Translating(count) => format!("translating {} sentances", count),
+            TranslationStatus::Offline {
+                attempt, retry_in, ..
+            } => format!(
+                "translation server offline, retry {} in {}s",
+                attempt + 1,
+                retry_in.as_secs()
+            ),
+            TranslationStatus::Failed(error) => format!("translation failed: {}", error),
+        }
+    }
+}
+
+// sentances without a translation yet, each one once
+pub fn pending(lesson: &[Entry]) -> Vec<Rc<str>> {
+    let mut pending: Vec<Rc<str>> = Vec::new();
+    lesson.iter().for_each(|entry| {
+        if entry.translations.is_empty() && !pending.contains(&entry.text) {
+            pending.push(entry.text.clone());
+        }
+    });
+    pending
+}
+
+// whether a sentance got a translation
+fn apply(lesson: &mut [Entry], texts: &[Rc<str>], translated: Vec<Variants>) -> bool {
+    let mut changed = false;
+    texts.iter().zip(translated).for_each(|(text, variants)| {
+        lesson
+            .iter_mut()
+            .filter(|entry| &entry.text == text && entry.translations.is_empty())
+            .for_each(|entry| {
+                entry.translations = Translations::machine(variants.clone());
+                changed |= !entry.translations.is_empty();
+            });
+    });
+    changed
+}
+
+// translates the pending sentances of the lesson in the background and retries with
+// backoff while the server is unreachable, typing never waits for it
+#[derive(Clone, Copy)]
+pub struct TranslationQueue {
+    pub lesson: RwSignal<Lesson>,
+    pub status: RwSignal<TranslationStatus>,
+    settings: RwSignal<Settings>,
+    cache: StoredValue<Rc<RefCell<TranslationCache>>>,
+    generation: StoredValue<usize>,
+    backoff: Backoff,
+}
+
+impl TranslationQueue {
+    pub fn new(
+        lesson: RwSignal<Lesson>,
+        settings: RwSignal<Settings>,
+        cache: StoredValue<Rc<RefCell<TranslationCache>>>,
+    ) -> Self {
+        TranslationQueue {
+            lesson,
+            status: create_rw_signal(TranslationStatus::Idle),
+            settings,
+            cache,
+            generation: store_value(0),
+            backoff: Backoff::default(),
+        }
+    }
+
+    // a newer start cancels the retries of the previous one
+    pub fn start(self) {
+        self.generation.update_value(|generation| *generation += 1);
+        self.run(self.generation.get_value(), 0);
+    }
+
+    // only the translations change, the typing states of the lesson store are kept
+    fn fill(self, texts: &[Rc<str>], translated: Vec<Variants>) {
+        let mut lesson = self.lesson.get_untracked();
+        if apply(&mut lesson.sentances, texts, translated) {
+            self.lesson.set(lesson);
+        }
+    }
+
+    fn run(self, generation: usize, attempt: u32) {
+        if self.generation.get_value() != generation {
+            return;
+        }
+        let (pair, texts) = self
+            .lesson
+            .with_untracked(|lesson| (lesson.pair.clone(), pending(&lesson.sentances)));
+        if texts.is_empty() {
+            self.status.set(TranslationStatus::Idle);
+            return;
+        }
+        self.status.set(TranslationStatus::Translating(texts.len()));
+        let translator = CachedTranslator {
+            provider: self
+                .settings
+                .with_untracked(|settings| settings.translator.clone()),
+            cache: self.cache.get_value(),
+        };
+        spawn_local(async move {
+            let sentances: Vec<&str> = texts.iter().map(|text| &**text).collect();
+            let result = translator.translate(&pair, &sentances).await;
+            logging::log!("translation cache {:?}", translator.cache.borrow().stats());
+            if self.generation.get_value() != generation {
+                return;
+            }
+            match result {
+                Ok(translated) => {
+                    // an empty answer would stay pending forever, it fails until the next start
+                    let untranslated = translated
+                        .iter()
+                        .filter(|variants| variants.is_empty())
+                        .count();
+                    self.fill(&texts, translated);
+                    self.status.set(if untranslated == 0 {
+                        TranslationStatus::Idle
+                    } else {
+                        TranslationStatus::Failed(format!(
+                            "no translation for {} sentances",
+                            untranslated
+                        ))
+                    });
+                }
+                Err(error) if error.is_retryable() => {
+                    let retry_in = self.backoff.delay(attempt);
+                    logging::warn!("translation failed: {}, retry in {:?}", error, retry_in);
+                    self.status.set(TranslationStatus::Offline {
+                        attempt,
+                        retry_in,
+                        error: error.to_string(),
+                    });
+                    set_timeout(move || self.run(generation, attempt + 1), retry_in);
+                }
+                Err(error) => {
+                    logging::error!("translation failed: {}", error);
+                    self.status
+                        .set(TranslationStatus::Failed(error.to_string()));
+                }
+            }
+        });
+    }
+}
+
+#[component]
+pub fn StatusIndicator(status: RwSignal<TranslationStatus>) -> impl IntoView {
+    let class = move || match status() {
+        TranslationStatus::Idle => "text-green-600",
+        TranslationStatus::Translating(_) => "text-gray-500",
+        TranslationStatus::Offline { .. } => "text-amber-600",
+        TranslationStatus::Failed(_) => "text-red-600",
+    };
+    view! {
+        <div class=move || format!("text-xl lg:text-base font-normal {}", class())>
+            {move || status.with(TranslationStatus::message)}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::alignments::AlignmentStore;
+    use crate::cache::CacheStorage;
+    use crate::lesson::LanguagePair;
+    use crate::store::LessonStore;
+
+    fn entry(text: &str, translation: &str) -> Entry {
+        Entry {
+            text: text.into(),
+            translations: Translations::machine(
+                Some(translation.to_string()).filter(|translation| !translation.is_empty()),
+            ),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let backoff = Backoff::default();
+        let delays: Vec<u64> = (0..7)
+            .map(|attempt| backoff.delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(backoff.delay(u32::MAX), backoff.max);
+    }
+
+    #[test]
+    fn pending_sentances_are_filled_in() {
+        let mut lesson = vec![
+            entry("Ja.", ""),
+            entry("Nein.", "No."),
+            entry("Ja.", ""),
+            entry("Gut.", ""),
+        ];
+        let texts = pending(&lesson);
+        assert_eq!(texts, vec![Rc::from("Ja."), Rc::from("Gut.")]);
+        assert!(apply(
+            &mut lesson,
+            &texts,
+            vec![vec!["Yes.".to_string()], vec!["Good.".to_string()]],
+        ));
+        assert_eq!(
+            lesson,
+            vec![
+                entry("Ja.", "Yes."),
+                entry("Nein.", "No."),
+                entry("Ja.", "Yes."),
+                entry("Gut.", "Good."),
+            ]
+        );
+        assert!(pending(&lesson).is_empty());
+
+        let mut lesson = vec![entry("Hm.", "")];
+        let texts = pending(&lesson);
+        assert!(!apply(&mut lesson, &texts, vec![vec![]]));
+        assert_eq!(pending(&lesson), vec![Rc::from("Hm.")]);
+    }
+
+    #[test]
+    fn batches_keep_the_typing_progress() {
+        let runtime = create_runtime();
+        let lesson = create_rw_signal(Lesson::from_text("Ja. Nein.", LanguagePair::default()));
+        let store = LessonStore::new(lesson, AlignmentStore::open(CacheStorage::Memory));
+        let cache = store_value(Rc::new(RefCell::new(TranslationCache::open(
+            CacheStorage::Memory,
+        ))));
+        let queue = TranslationQueue::new(lesson, create_rw_signal(Settings::default()), cache);
+        let state = store.state(0).unwrap();
+        state.update(|state| {
+            state.type_char('J');
+        });
+
+        let texts = lesson.with_untracked(|lesson| pending(&lesson.sentances));
+        queue.fill(&texts[..1], vec![vec!["Yes.".to_string()]]);
+        assert_eq!(store.state(0), Some(state));
+        assert_eq!(state.with_untracked(|state| state.results().correct), 1);
+        assert_eq!(store.translations(0).get_untracked().variants.len(), 1);
+        assert!(store.translations(1).get_untracked().is_empty());
+        runtime.dispose();
+    }
+}
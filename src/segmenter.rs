@@ -0,0 +1,244 @@
+use std::collections::BTreeSet;
+
+pub const GERMAN_ABBREVIATIONS: [&str; 30] = [
+    "abs", "bspw", "bzgl", "bzw", "ca", "d.h", "dr", "ebd", "etc", "evtl", "geb", "ggf", "hr",
+    "inkl", "jh", "mio", "mrd", "nr", "o.ä", "prof", "s", "sog", "st", "str", "u.a", "u.ä", "usw",
+    "vgl", "z.b", "z.t",
+];
+
+pub const FRENCH_ABBREVIATIONS: [&str; 12] = [
+    "av", "bd", "cf", "env", "etc", "ex", "m", "mlle", "mme", "n°", "p", "p.ex",
+];
+
+pub const DUTCH_ABBREVIATIONS: [&str; 12] = [
+    "bijv", "blz", "bv", "d.w.z", "dhr", "dr", "enz", "ir", "mevr", "mr", "nr", "o.a",
+];
+
+const TERMINATORS: [char; 4] = ['.', '!', '?', '…'];
+const CLOSING: [char; 9] = ['“', '”', '"', '»', '«', '\'', '’', ')', ']'];
+
+// abbreviations are stored lowercase and without the final dot, like "z.b" for "z.B."
+#[derive(Clone, PartialEq, Debug)]
+pub struct Segmenter {
+    abbreviations: BTreeSet<String>,
+}
+
+impl Default for Segmenter {
+    fn default() -> Self {
+        Self::new(GERMAN_ABBREVIATIONS)
+    }
+}
+
+impl Segmenter {
+    pub fn new<'a>(abbreviations: impl IntoIterator<Item = &'a str>) -> Self {
+        Segmenter {
+            abbreviations: abbreviations.into_iter().map(abbreviation).collect(),
+        }
+    }
+
+    // abbreviations of the source language, other languages split at every terminator
+    pub fn builtin(language: &str) -> Self {
+        match language {
+            "de" => Self::new(GERMAN_ABBREVIATIONS),
+            "fr" => Self::new(FRENCH_ABBREVIATIONS),
+            "nl" => Self::new(DUTCH_ABBREVIATIONS),
+            _ => Self::new([]),
+        }
+    }
+
+    pub fn with_abbreviation(mut self, value: &str) -> Self {
+        self.abbreviations.insert(abbreviation(value));
+        self
+    }
+
+    pub fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut sentances = Vec::new();
+        let mut start = 0;
+        let mut index = 0;
+        while index < chars.len() {
+            if !TERMINATORS.contains(&chars[index].1) {
+                index += 1;
+                continue;
+            }
+            let mut end = index;
+            while chars
+                .get(end + 1)
+                .is_some_and(|(_, c)| TERMINATORS.contains(c) || CLOSING.contains(c))
+            {
+                end += 1;
+            }
+            let after = chars.get(end + 1).map_or(text.len(), |(offset, _)| *offset);
+            if self.is_boundary(
+                &text[..chars[index].0],
+                &text[chars[index].0..after],
+                &text[after..],
+            ) {
+                sentances.push(&text[start..after]);
+                start = after;
+            }
+            index = end + 1;
+        }
+        sentances.push(&text[start..]);
+        sentances
+            .into_iter()
+            .map(str::trim)
+            .filter(|sentance| !sentance.is_empty())
+            .collect()
+    }
+
+    fn is_boundary(&self, before: &str, terminator: &str, after: &str) -> bool {
+        let Some(next) = after.trim_start().chars().next() else {
+            return true;
+        };
+        // a terminator glued to the following text, like "3.5" or "„Ja!“,", never ends a sentance
+        if !after.starts_with(char::is_whitespace) || next.is_lowercase() {
+            return false;
+        }
+        if terminator != "." {
+            return true;
+        }
+        let word: String = before
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || *c == '.')
+            .collect::<Vec<char>>()
+            .into_iter()
+            .rev()
+            .collect();
+        let word = word.trim_start_matches('.');
+        if self.abbreviations.contains(&word.to_lowercase()) {
+            return false;
+        }
+        // initials like "J. S. Bach"
+        let mut letters = word.chars();
+        if let (Some(letter), None) = (letters.next(), letters.next()) {
+            if letter.is_uppercase() {
+                return false;
+            }
+        }
+        // ordinals like "3. Oktober" or "19. Jahrhundert", years like "2024." still end a sentance
+        let ordinal =
+            !word.is_empty() && word.len() <= 2 && word.chars().all(|c| c.is_ascii_digit());
+        !(ordinal && next.is_alphabetic())
+    }
+}
+
+fn abbreviation(value: &str) -> String {
+    value.trim().trim_end_matches('.').to_lowercase()
+}
+
+pub fn sentances(text: &str) -> Vec<&str> {
+    Segmenter::default().segment(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_sentances() {
+        assert_eq!(
+            sentances("  Wie Sie Geld sparen. Und sogar welches verdienen!\n\nWarum? "),
+            vec![
+                "Wie Sie Geld sparen.",
+                "Und sogar welches verdienen!",
+                "Warum?"
+            ]
+        );
+        assert!(sentances(" \n ").is_empty());
+    }
+
+    #[test]
+    fn abbreviations() {
+        assert_eq!(
+            sentances("Strom, Gas usw. Dazu kommt z.B. Wasser, d.h. Die Kosten steigen. Dr. Weber sagt das."),
+            vec![
+                "Strom, Gas usw. Dazu kommt z.B. Wasser, d.h. Die Kosten steigen.",
+                "Dr. Weber sagt das."
+            ]
+        );
+        assert_eq!(
+            sentances("Das gilt ab Z.B. Morgen. Gut."),
+            vec!["Das gilt ab Z.B. Morgen.", "Gut."]
+        );
+    }
+
+    #[test]
+    fn configurable_abbreviations() {
+        let text = "Ruf an unter Tel. Neun Null. Danke.";
+        assert_eq!(
+            sentances(text),
+            vec!["Ruf an unter Tel.", "Neun Null.", "Danke."]
+        );
+        let segmenter = Segmenter::default().with_abbreviation("Tel.");
+        assert_eq!(
+            segmenter.segment(text),
+            vec!["Ruf an unter Tel. Neun Null.", "Danke."]
+        );
+        assert_eq!(Segmenter::new(["Dr."]).segment("Gas usw. Dazu").len(), 2);
+    }
+
+    #[test]
+    fn ordinals_and_numbers() {
+        assert_eq!(
+            sentances("Am 3. Oktober und im 19. Jahrhundert kostete es 3.5 Euro. Das war 2024. Dann nicht mehr."),
+            vec![
+                "Am 3. Oktober und im 19. Jahrhundert kostete es 3.5 Euro.",
+                "Das war 2024.",
+                "Dann nicht mehr."
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            sentances("„Kommst du?“, fragte sie. „Ja.“ Dann ging er. Sie rief: »Halt!« Er blieb."),
+            vec![
+                "„Kommst du?“, fragte sie.",
+                "„Ja.“",
+                "Dann ging er.",
+                "Sie rief: »Halt!«",
+                "Er blieb."
+            ]
+        );
+    }
+
+    #[test]
+    fn ellipses() {
+        assert_eq!(
+            sentances("Und dann … nichts. Oder doch... Vielleicht... aber wer weiß?! Niemand."),
+            vec![
+                "Und dann … nichts.",
+                "Oder doch...",
+                "Vielleicht... aber wer weiß?!",
+                "Niemand."
+            ]
+        );
+    }
+
+    #[test]
+    fn other_languages() {
+        assert_eq!(
+            Segmenter::builtin("nl").segment("Dat is bijv. een test. Goed."),
+            vec!["Dat is bijv. een test.", "Goed."]
+        );
+        assert_eq!(
+            Segmenter::builtin("fr").segment("Bonjour Mme. Dupont. Merci."),
+            vec!["Bonjour Mme. Dupont.", "Merci."]
+        );
+        assert_eq!(
+            Segmenter::builtin("xx").segment("Usw. Dann."),
+            vec!["Usw.", "Dann."]
+        );
+    }
+
+    #[test]
+    fn initials() {
+        assert_eq!(
+            sentances("Musik von J. S. Bach. Schön."),
+            vec!["Musik von J. S. Bach.", "Schön."]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::rc::Rc;
+
+use leptos::*;
+
+use crate::equivalence::{Equivalence, Leniency};
+use crate::lesson::{targets, LanguagePair};
+use crate::provider::{TranslationProvider, Translator};
+use crate::translate::TranslateClient;
+use crate::types::TypeState;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Settings {
+    pub rules: Rc<Equivalence>,
+    pub translator: Translator,
+    // the languages of the lesson, the rules follow its source and the target picked here
+    // is requested for the lesson
+    pub pair: LanguagePair,
+}
+
+impl Settings {
+    pub fn set_language(&mut self, language: &str) {
+        if let Some(rules) = Equivalence::builtin(language) {
+            self.rules = Rc::new(rules.with_level(self.rules.level));
+            self.set_source(language);
+        }
+    }
+    fn set_source(&mut self, language: &str) {
+        self.pair = LanguagePair::for_source(language, &self.pair.target);
+    }
+    // keeps the level that is set, the table has to be for the language of the lesson
+    pub fn set_custom_rules(&mut self, json: &str) -> Result<(), String> {
+        let rules = Equivalence::custom(json)?;
+        if rules.language != self.pair.source {
+            return Err(format!(
+                "typing rules for \"{}\" do not match the lesson language \"{}\"",
+                rules.language, self.pair.source
+            ));
+        }
+        self.rules = Rc::new(rules.with_level(self.rules.level));
+        Ok(())
+    }
+    pub fn set_target(&mut self, language: &str) {
+        if language != self.pair.source {
+            self.pair.target = language.to_string();
+        }
+    }
+    pub fn set_pair(&mut self, pair: &LanguagePair) {
+        self.set_language(&pair.source);
+        self.set_target(&pair.target);
+    }
+    pub fn set_leniency(&mut self, level: Leniency) {
+        self.rules = Rc::new((*self.rules).clone().with_level(level));
+    }
+    pub fn set_translator(&mut self, name: &str) {
+        if self.translator.name() != name {
+            if let Some(translator) = Translator::from_name(name) {
+                self.translator = translator;
+            }
+        }
+    }
+    pub fn set_translate_url(&mut self, url: &str) {
+        if let Translator::Marian(client) = &mut self.translator {
+            client.base_url = TranslateClient::new(url).base_url;
+        }
+    }
+}
+
+pub(crate) fn follow_settings(set_store: WriteSignal<TypeState>) {
+    if let Some(settings) = use_context::<RwSignal<Settings>>() {
+        create_effect(move |_| {
+            let rules = settings.with(|settings| settings.rules.clone());
+            set_store.update(|store| store.set_rules(rules));
+        });
+    }
+}
+
+#[component]
+pub fn SettingsPanel(settings: RwSignal<Settings>) -> impl IntoView {
+    view! {
+        <div class="flex gap-4 text-xl lg:text-base font-normal text-gray-700">
+            <select on:change=move |event| {
+                settings.update(|settings| settings.set_target(&event_target_value(&event)))
+            }>
+                {move || {
+                    let source = settings.with(|settings| settings.pair.source.clone());
+                    targets(&source)
+                        .map(|language| {
+                            view! {
+                                <option
+                                    value=language
+                                    selected=move || settings.with(|settings| settings.pair.target == language)
+                                >
+                                    {format!("→ {}", language)}
+                                </option>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </select>
+            <select on:change=move |event| {
+                if let Some(level) = Leniency::from_name(&event_target_value(&event)) {
+                    settings.update(|settings| settings.set_leniency(level))
+                }
+            }>
+                {Leniency::ALL
+                    .into_iter()
+                    .map(|level| {
+                        view! {
+                            <option
+                                value=level.name()
+                                selected=move || settings.with(|settings| settings.rules.level == level)
+                            >
+                                {level.name()}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            <select on:change=move |event| {
+                settings.update(|settings| settings.set_translator(&event_target_value(&event)))
+            }>
+                {Translator::NAMES
+                    .into_iter()
+                    .map(|name| {
+                        view! {
+                            <option
+                                value=name
+                                selected=move || settings.with(|settings| settings.translator.name() == name)
+                            >
+                                {name}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            {move || {
+                settings
+                    .with(|settings| match &settings.translator {
+                        Translator::Marian(client) => Some(client.base_url.clone()),
+                        Translator::Offline(_) => None,
+                    })
+                    .map(|url| {
+                        view! {
+                            <input
+                                type="url"
+                                placeholder="translation server"
+                                value=url
+                                on:change=move |event| {
+                                    settings.update(|settings| settings.set_translate_url(&event_target_value(&event)))
+                                }
+                            />
+                        }
+                    })
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_and_target_differ() {
+        let mut settings = Settings::default();
+        settings.set_target("de");
+        assert_eq!(settings.pair, LanguagePair::default());
+        settings.set_target("fr");
+        settings.set_language("fr");
+        assert_eq!(settings.pair, LanguagePair::new("fr", "en").unwrap());
+        assert_eq!(settings.rules.language, "fr");
+        settings.set_pair(&LanguagePair::new("nl", "fr").unwrap());
+        assert_eq!(settings.pair, LanguagePair::new("nl", "fr").unwrap());
+    }
+
+    #[test]
+    fn custom_rules_are_for_the_lesson_language() {
+        let mut settings = Settings::default();
+        assert!(settings
+            .set_custom_rules(r#"{"language": "de", "pairs": {"ß": "z"}}"#)
+            .is_ok());
+        assert!(settings.rules.matches("z", "ß"));
+        assert_eq!(
+            settings.set_custom_rules(r#"{"language": "fr"}"#),
+            Err("typing rules for \"fr\" do not match the lesson language \"de\"".to_string())
+        );
+        assert_eq!(settings.rules.language, "de");
+    }
+}
@@ -0,0 +1,148 @@
+use std::rc::Rc;
+
+use leptos::*;
+
+use crate::alignments::AlignmentStore;
+use crate::lesson::{Alignment, Lesson, Translations};
+use crate::progress::follow_progress;
+use crate::settings::follow_settings;
+use crate::types::TypeState;
+
+// the typing state of a sentance follows the settings and is saved as it changes
+pub fn typing_state(index: usize, text: Rc<str>) -> RwSignal<TypeState> {
+    let state = create_rw_signal(TypeState::new(&text));
+    follow_settings(state.write_only());
+    follow_progress(index, text, state.read_only(), state.write_only());
+    state
+}
+
+// everything the views of a lesson share, the list and the popup of a sentance read
+// and write the same typing state and alignments
+#[derive(Clone, Copy)]
+pub struct LessonStore {
+    pub lesson: RwSignal<Lesson>,
+    // only a new text rebuilds the states, changed translations keep the typing progress
+    pub sentances: Memo<Vec<(Rc<str>, RwSignal<TypeState>)>>,
+    alignments: StoredValue<AlignmentStore>,
+}
+
+impl LessonStore {
+    pub fn new(lesson: RwSignal<Lesson>, alignments: AlignmentStore) -> Self {
+        let texts = create_memo(move |_| {
+            lesson.with(|lesson| {
+                lesson
+                    .sentances
+                    .iter()
+                    .map(|entry| entry.text.clone())
+                    .collect::<Vec<_>>()
+            })
+        });
+        // states of a previous text are disposed when the memo runs again
+        let sentances = create_memo(move |_| {
+            texts
+                .get()
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| (text.clone(), typing_state(index, text)))
+                .collect()
+        });
+        LessonStore {
+            lesson,
+            sentances,
+            alignments: store_value(alignments),
+        }
+    }
+
+    pub fn state(self, index: usize) -> Option<RwSignal<TypeState>> {
+        self.sentances
+            .with_untracked(|sentances| sentances.get(index).map(|(_, state)| *state))
+    }
+
+    // a memo, so translations landing for one sentance leave the others alone
+    pub fn translations(self, index: usize) -> Signal<Translations> {
+        Signal::from(create_memo(move |_| {
+            self.lesson.with(|lesson| {
+                lesson
+                    .sentances
+                    .get(index)
+                    .map(|entry| entry.translations.clone())
+                    .unwrap_or_default()
+            })
+        }))
+    }
+
+    pub fn set_translations(self, index: usize, translations: Translations) {
+        self.lesson.update(|lesson| {
+            if let Some(entry) = lesson.sentances.get_mut(index) {
+                entry.translations = translations;
+            }
+        });
+    }
+
+    pub fn alignments(self, text: &str, translation: &str) -> Vec<Alignment> {
+        self.alignments
+            .with_value(|alignments| alignments.get(text, translation))
+    }
+
+    pub fn set_alignments(self, text: &str, translation: &str, changed: Vec<Alignment>) {
+        self.alignments
+            .update_value(|alignments| alignments.save(text, translation, changed));
+    }
+
+    // an imported lesson brings its own alignments
+    pub fn load(self, lesson: Lesson) {
+        self.alignments
+            .update_value(|alignments| alignments.remember(&lesson));
+        self.lesson.set(lesson);
+    }
+
+    pub fn export(self) -> Lesson {
+        let mut lesson = self.lesson.get_untracked();
+        self.alignments
+            .with_value(|alignments| alignments.fill(&mut lesson));
+        lesson
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cache::CacheStorage;
+    use crate::lesson::LanguagePair;
+
+    #[test]
+    fn states_survive_translations_but_not_a_new_text() {
+        let runtime = create_runtime();
+        let lesson = create_rw_signal(Lesson::from_text("Ja. Nein.", LanguagePair::default()));
+        let store = LessonStore::new(lesson, AlignmentStore::open(CacheStorage::Memory));
+        let state = store.state(0).unwrap();
+        state.update(|state| {
+            state.type_char('J');
+        });
+        store.set_translations(0, Translations::machine(["Yes.".to_string()]));
+        assert_eq!(store.state(0), Some(state));
+        assert_eq!(
+            store
+                .state(0)
+                .unwrap()
+                .with_untracked(|state| state.results().correct),
+            1
+        );
+        assert_eq!(store.translations(0).get_untracked().variants.len(), 1);
+
+        // the queue fills in every pending sentance at once
+        let other = store.translations(1);
+        lesson.update(|lesson| {
+            lesson.sentances[0].translations = Translations::machine(["Yeah.".to_string()]);
+        });
+        assert_eq!(store.state(0), Some(state));
+        assert_eq!(state.with_untracked(|state| state.results().correct), 1);
+        assert!(other.get_untracked().is_empty());
+
+        lesson.set(Lesson::from_text("Gut.", LanguagePair::default()));
+        assert_ne!(store.state(0), Some(state));
+        assert_eq!(store.state(1), None);
+        runtime.dispose();
+    }
+}
@@ -0,0 +1,144 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+    Word,
+    Number,
+    Punctuation,
+    Whitespace,
+}
+
+// start and end are byte offsets into the tokenized text
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token<'_> {
+    pub fn is_word(&self) -> bool {
+        matches!(self.kind, TokenKind::Word | TokenKind::Number)
+    }
+}
+
+fn kind(segment: &str) -> TokenKind {
+    if segment.chars().all(char::is_whitespace) {
+        TokenKind::Whitespace
+    } else if segment.chars().any(char::is_alphabetic) {
+        TokenKind::Word
+    } else if segment.chars().any(char::is_numeric) {
+        TokenKind::Number
+    } else {
+        TokenKind::Punctuation
+    }
+}
+
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    text.split_word_bound_indices()
+        .for_each(|(start, segment)| {
+            let kind = kind(segment);
+            let end = start + segment.len();
+            match tokens.last_mut() {
+                Some(last)
+                    if last.kind == TokenKind::Whitespace && kind == TokenKind::Whitespace =>
+                {
+                    last.end = end;
+                    last.text = &text[last.start..end];
+                }
+                _ => tokens.push(Token {
+                    kind,
+                    text: segment,
+                    start,
+                    end,
+                }),
+            }
+        });
+    tokens
+}
+
+// runs of tokens between whitespace, which is what the learner types as one word
+pub fn typing_words(text: &str) -> Vec<Token<'_>> {
+    let mut words: Vec<Token> = Vec::new();
+    let mut glued = false;
+    tokenize(text).into_iter().for_each(|token| {
+        if token.kind == TokenKind::Whitespace {
+            glued = false;
+            return;
+        }
+        match words.last_mut() {
+            Some(last) if glued => {
+                last.end = token.end;
+                last.text = &text[last.start..token.end];
+                if last.kind == TokenKind::Punctuation {
+                    last.kind = token.kind;
+                }
+            }
+            _ => words.push(token),
+        }
+        glued = true;
+    });
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(tokens: &[Token]) -> Vec<String> {
+        tokens.iter().map(|token| token.text.to_string()).collect()
+    }
+
+    #[test]
+    fn kinds_and_offsets() {
+        let tokens = tokenize("Am 3. Oktober, 2024  kam er.");
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| (token.kind, token.text))
+                .collect::<Vec<_>>(),
+            vec![
+                (TokenKind::Word, "Am"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Number, "3"),
+                (TokenKind::Punctuation, "."),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Word, "Oktober"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Number, "2024"),
+                (TokenKind::Whitespace, "  "),
+                (TokenKind::Word, "kam"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Word, "er"),
+                (TokenKind::Punctuation, "."),
+            ]
+        );
+        let oktober = &tokens[5];
+        assert_eq!((oktober.start, oktober.end), (6, 13));
+    }
+
+    #[test]
+    fn typing_words_ignore_repeated_whitespace() {
+        let words = typing_words("Auf  und\tAb,\n„Strom“ – 3,5 kWh ");
+        assert_eq!(
+            texts(&words),
+            vec!["Auf", "und", "Ab,", "„Strom“", "–", "3,5", "kWh"]
+        );
+        assert_eq!(words[3].kind, TokenKind::Word);
+        assert_eq!(words[4].kind, TokenKind::Punctuation);
+        assert_eq!(words[5].kind, TokenKind::Number);
+        assert_eq!(&"Auf  und\tAb,"[words[2].start..words[2].end], "Ab,");
+    }
+
+    #[test]
+    fn punctuation_is_separate_from_words() {
+        let words: Vec<Token> = tokenize("money, (and) earn.")
+            .into_iter()
+            .filter(Token::is_word)
+            .collect();
+        assert_eq!(texts(&words), vec!["money", "and", "earn"]);
+    }
+}
@@ -0,0 +1,409 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lesson::LanguagePair;
+
+pub const DEFAULT_URL: &str = "http://localhost:5000";
+// the server loads MARIAN_MODELS-source-target for a pair, like translate_server.py does
+pub const MARIAN_MODELS: &str = "Helsinki-NLP/opus-mt";
+
+// the n best translations of one sentance, best first
+pub type Variants = Vec<String>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TranslateError {
+    Timeout,
+    Network(String),
+    Status(u16),
+    Decode(String),
+    Mismatch { sent: usize, received: usize },
+}
+
+impl fmt::Display for TranslateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranslateError::Timeout => write!(f, "translation request timed out"),
+            TranslateError::Network(message) => {
+                write!(f, "translation request failed: {}", message)
+            }
+            TranslateError::Status(status) => write!(f, "translation server answered {}", status),
+            TranslateError::Decode(message) => {
+                write!(f, "invalid translation response: {}", message)
+            }
+            TranslateError::Mismatch { sent, received } => {
+                write!(
+                    f,
+                    "sent {} sentances but got {} translations",
+                    sent, received
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TranslateError {}
+
+impl TranslateError {
+    // errors that may go away when the same request is sent again later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TranslateError::Timeout | TranslateError::Network(_) => true,
+            TranslateError::Status(status) => *status == 429 || *status >= 500,
+            TranslateError::Decode(_) | TranslateError::Mismatch { .. } => false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TranslateRequest<'a> {
+    src: &'a [&'a str],
+    source: &'a str,
+    target: &'a str,
+    variants: usize,
+}
+
+// servers that do not know about variants only answer translated
+#[derive(Deserialize)]
+struct TranslateResponse {
+    translated: Vec<String>,
+    #[serde(default)]
+    variants: Option<Vec<Variants>>,
+}
+
+// client for POST /translate of translate_server.py, sentances are sent in batches of batch_size
+#[derive(Clone, PartialEq, Debug)]
+pub struct TranslateClient {
+    pub base_url: String,
+    pub batch_size: usize,
+    pub timeout: Duration,
+    pub variants: usize,
+}
+
+impl Default for TranslateClient {
+    fn default() -> Self {
+        Self::new(DEFAULT_URL)
+    }
+}
+
+impl TranslateClient {
+    pub fn new(base_url: &str) -> Self {
+        TranslateClient {
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            batch_size: 16,
+            timeout: Duration::from_secs(30),
+            variants: 3,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_variants(mut self, variants: usize) -> Self {
+        self.variants = variants.max(1);
+        self
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("{}/translate", self.base_url)
+    }
+
+    pub async fn translate(
+        &self,
+        pair: &LanguagePair,
+        sentances: &[&str],
+    ) -> Result<Vec<Variants>, TranslateError> {
+        let mut translated = Vec::with_capacity(sentances.len());
+        for batch in sentances.chunks(self.batch_size) {
+            let request = TranslateRequest {
+                src: batch,
+                source: &pair.source,
+                target: &pair.target,
+                variants: self.variants,
+            };
+            let received = self.post(&request).await?;
+            if received.len() != batch.len() {
+                return Err(TranslateError::Mismatch {
+                    sent: batch.len(),
+                    received: received.len(),
+                });
+            }
+            translated.extend(received);
+        }
+        Ok(translated)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn post(&self, request: &TranslateRequest<'_>) -> Result<Vec<Variants>, TranslateError> {
+        use gloo_net::http::Request;
+        use gloo_net::Error;
+
+        let timeout = u32::try_from(self.timeout.as_millis()).unwrap_or(u32::MAX);
+        let signal = web_sys::AbortSignal::timeout_with_u32(timeout);
+        let network = |error: Error| match error {
+            Error::JsError(error) if error.name == "TimeoutError" || error.name == "AbortError" => {
+                TranslateError::Timeout
+            }
+            error => TranslateError::Network(error.to_string()),
+        };
+        let response = Request::post(&self.endpoint())
+            .abort_signal(Some(&signal))
+            .json(request)
+            .map_err(|error| TranslateError::Decode(error.to_string()))?
+            .send()
+            .await
+            .map_err(network)?;
+        let body = response.text().await.map_err(network)?;
+        decode(response.status(), &body)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn post(&self, request: &TranslateRequest<'_>) -> Result<Vec<Variants>, TranslateError> {
+        let body = serde_json::to_string(request)
+            .map_err(|error| TranslateError::Decode(error.to_string()))?;
+        let response = ureq::AgentBuilder::new()
+            .timeout(self.timeout)
+            .build()
+            .post(&self.endpoint())
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+        let response = match response {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _response)) => {
+                return Err(TranslateError::Status(status))
+            }
+            Err(ureq::Error::Transport(transport)) => return Err(transport_error(&transport)),
+        };
+        let status = response.status();
+        let body = response.into_string().map_err(|error| io_error(&error))?;
+        decode(status, &body)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn transport_error(transport: &ureq::Transport) -> TranslateError {
+    match std::error::Error::source(transport).and_then(|source| source.downcast_ref()) {
+        Some(error) => io_error(error),
+        None => TranslateError::Network(transport.to_string()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn io_error(error: &std::io::Error) -> TranslateError {
+    match error.kind() {
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => TranslateError::Timeout,
+        _ => TranslateError::Network(error.to_string()),
+    }
+}
+
+fn decode(status: u16, body: &str) -> Result<Vec<Variants>, TranslateError> {
+    if !(200..300).contains(&status) {
+        return Err(TranslateError::Status(status));
+    }
+    let response = serde_json::from_str::<TranslateResponse>(body)
+        .map_err(|error| TranslateError::Decode(error.to_string()))?;
+    let variants = response.variants.unwrap_or_else(|| {
+        response
+            .translated
+            .into_iter()
+            .map(|translated| vec![translated])
+            .collect()
+    });
+    // a sentance without any text left has no translation, see queue::TranslationQueue
+    Ok(variants
+        .into_iter()
+        .map(|variants| {
+            variants
+                .into_iter()
+                .filter(|variant| !variant.trim().is_empty())
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use futures::executor::block_on;
+
+    // answers each request with the status, body and delay returned by respond and
+    // sends every request to the returned receiver
+    fn mock_server(
+        respond: impl Fn(Vec<String>) -> (u16, String, Duration) + Send + 'static,
+    ) -> (String, mpsc::Receiver<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let src: Vec<String> = serde_json::from_value(request["src"].clone()).unwrap();
+                let (status, body, delay) = respond(src);
+                let _ = sender.send(request);
+                thread::sleep(delay);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        (url, receiver)
+    }
+
+    fn upper(src: Vec<String>) -> (u16, String, Duration) {
+        let translated: Vec<String> = src.iter().map(|item| item.to_uppercase()).collect();
+        (
+            200,
+            serde_json::json!({ "translated": translated }).to_string(),
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn endpoint() {
+        assert_eq!(
+            TranslateClient::new("http://example.com:5000/ ").endpoint(),
+            "http://example.com:5000/translate"
+        );
+        assert_eq!(TranslateClient::default().batch_size, 16);
+        assert_eq!(TranslateClient::default().with_batch_size(0).batch_size, 1);
+    }
+
+    #[test]
+    fn retryable_errors() {
+        assert!(TranslateError::Timeout.is_retryable());
+        assert!(TranslateError::Network("refused".to_string()).is_retryable());
+        assert!(TranslateError::Status(503).is_retryable());
+        assert!(TranslateError::Status(429).is_retryable());
+        assert!(!TranslateError::Status(404).is_retryable());
+        assert!(!TranslateError::Decode("eof".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn translates_in_batches() {
+        let (url, requests) = mock_server(upper);
+        let client = TranslateClient::new(&url).with_batch_size(2);
+        let pair = LanguagePair::new("nl", "de").unwrap();
+        let translated = block_on(client.translate(&pair, &["eins", "zwei", "drei"])).unwrap();
+        assert_eq!(translated, vec![vec!["EINS"], vec!["ZWEI"], vec!["DREI"]]);
+        assert_eq!(
+            requests.recv().unwrap(),
+            serde_json::json!({"src": ["eins", "zwei"], "source": "nl", "target": "de", "variants": 3})
+        );
+        assert_eq!(requests.recv().unwrap()["src"], serde_json::json!(["drei"]));
+        assert_eq!(
+            block_on(client.translate(&LanguagePair::default(), &[])).unwrap(),
+            Vec::<Variants>::new()
+        );
+    }
+
+    #[test]
+    fn n_best_variants() {
+        let (url, requests) = mock_server(|_src| {
+            let body = serde_json::json!({
+                "translated": ["one"],
+                "variants": [["one", "a single one"]],
+            });
+            (200, body.to_string(), Duration::ZERO)
+        });
+        let client = TranslateClient::new(&url).with_variants(2);
+        assert_eq!(
+            block_on(client.translate(&LanguagePair::default(), &["eins"])).unwrap(),
+            vec![vec!["one", "a single one"]]
+        );
+        assert_eq!(requests.recv().unwrap()["variants"], 2);
+    }
+
+    #[test]
+    fn blank_translations_are_dropped() {
+        let body = r#"{"translated": ["", "two"], "variants": [["", " "], ["two", ""]]}"#;
+        assert_eq!(decode(200, body), Ok(vec![vec![], vec!["two".to_string()]]));
+        assert_eq!(decode(200, r#"{"translated": [""]}"#), Ok(vec![vec![]]));
+    }
+
+    #[test]
+    fn server_errors() {
+        let (url, _requests) = mock_server(|_src| (500, "oops".to_string(), Duration::ZERO));
+        let client = TranslateClient::new(&url);
+        assert_eq!(
+            block_on(client.translate(&LanguagePair::default(), &["eins"])),
+            Err(TranslateError::Status(500))
+        );
+
+        let (url, _requests) = mock_server(|_src| (200, "{}".to_string(), Duration::ZERO));
+        let client = TranslateClient::new(&url);
+        assert!(matches!(
+            block_on(client.translate(&LanguagePair::default(), &["eins"])),
+            Err(TranslateError::Decode(_))
+        ));
+
+        let (url, _requests) =
+            mock_server(|_src| (200, r#"{"translated": []}"#.to_string(), Duration::ZERO));
+        let client = TranslateClient::new(&url);
+        assert_eq!(
+            block_on(client.translate(&LanguagePair::default(), &["eins"])),
+            Err(TranslateError::Mismatch {
+                sent: 1,
+                received: 0
+            })
+        );
+    }
+
+    #[test]
+    fn timeouts_and_unreachable_servers() {
+        let (url, _requests) = mock_server(|src| {
+            let (status, body, _delay) = upper(src);
+            (status, body, Duration::from_millis(500))
+        });
+        let client = TranslateClient::new(&url).with_timeout(Duration::from_millis(100));
+        assert_eq!(
+            block_on(client.translate(&LanguagePair::default(), &["eins"])),
+            Err(TranslateError::Timeout)
+        );
+
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = TranslateClient::new(&format!("http://127.0.0.1:{}", port));
+        assert!(matches!(
+            block_on(client.translate(&LanguagePair::default(), &["eins"])),
+            Err(TranslateError::Network(_))
+        ));
+    }
+}
@@ -1,53 +1,813 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::equivalence::{Equivalence, Match, Transliteration};
+use crate::tokenizer::typing_words;
+
+// one grapheme cluster of the reference text, which may span several chars
 #[derive(Clone, PartialEq)]
 pub struct CharState {
     pub char_index: usize,
-    pub reference_char: char,
-    pub typed_char: Option<char>,
+    pub reference: String,
+    pub typed: Option<String>,
+    pub quality: Match,
+    pub pending: String,
+    pub transliteration: Option<String>,
 }
 
 impl CharState {
-    fn new(char_index: usize, reference_char: char) -> Self {
+    fn new(char_index: usize, reference: &str) -> Self {
         CharState {
             char_index,
-            reference_char,
-            typed_char: None,
+            reference: reference.to_string(),
+            typed: None,
+            quality: Match::Wrong,
+            pending: String::new(),
+            transliteration: None,
         }
     }
-    pub fn typed(&mut self, typed_char: char) {
-        self.typed_char = Some(typed_char);
+    pub fn typed(&mut self, typed: &str, rules: &Equivalence) {
+        self.quality = rules.quality(typed, &self.reference);
+        self.typed = Some(rules.normalize(typed));
+        self.transliteration = None;
+    }
+    pub fn transliterated(&mut self, sequence: String) {
+        self.typed = sequence.graphemes(true).next().map(str::to_string);
+        self.quality = Match::DiacriticLenient;
+        self.transliteration = Some(sequence);
     }
     pub fn backspace(&mut self) {
-        self.typed_char = None
+        self.typed = None;
+        self.quality = Match::Wrong;
+        self.pending.clear();
+        self.transliteration = None;
+    }
+    fn rescore(&mut self, rules: &Equivalence) {
+        match (&self.transliteration, self.typed.clone()) {
+            (Some(sequence), _) => {
+                self.quality = match rules.transliterate(sequence, &self.reference) {
+                    Transliteration::Complete => Match::DiacriticLenient,
+                    _ => Match::Wrong,
+                }
+            }
+            (None, Some(typed)) => self.typed(&typed, rules),
+            (None, None) => {}
+        }
+    }
+    pub fn is_correct(&self) -> bool {
+        self.typed.is_some() && self.quality != Match::Wrong
+    }
+    pub fn is_lenient(&self) -> bool {
+        self.is_correct() && self.quality != Match::Exact
+    }
+    pub fn typed_text(&self) -> String {
+        self.transliteration
+            .clone()
+            .or_else(|| self.typed.clone())
+            .unwrap_or_default()
     }
 }
 #[derive(Clone)]
 pub struct WordState {
     pub char_index: usize,
+    pub start: usize,
     pub data: Vec<CharState>,
 }
+
+impl WordState {
+    pub fn is_full(&self) -> bool {
+        self.char_index >= self.data.len()
+    }
+    pub fn key(&self) -> String {
+        let correct = self.data.iter().filter(|c| c.is_correct()).count();
+        let lenient = self.data.iter().filter(|c| c.is_lenient()).count();
+        format!("{}-{}-{}", self.char_index, correct, lenient)
+    }
+}
 #[derive(Clone)]
 pub struct TypeState {
     pub word_index: usize,
     pub data: Vec<WordState>,
     pub focus: bool,
+    pub composition: Option<String>,
+    pub rules: Rc<Equivalence>,
+    pub timing: Timing,
+}
+
+// pauses longer than this count as a break, not as typing time
+const IDLE_MS: f64 = 10_000.0;
+
+// keystrokes of one sentance, times are milliseconds since any fixed origin. only the
+// active time is saved, the last keystroke is forgotten on reload so the time away is
+// not counted. typed also counts what was deleted again later, corrected counts the
+// wrong chars that were deleted
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timing {
+    pub active_ms: f64,
+    #[serde(skip)]
+    pub last: Option<f64>,
+    pub typed: usize,
+    pub corrected: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cursor {
+    pub word_index: usize,
+    pub char_index: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Correct,
+    Lenient,
+    Wrong,
+    Deleted,
+    Moved(usize),
+    Pending,
+    Completed,
+    Ignored,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TypeResults {
+    pub correct: usize,
+    pub lenient: usize,
+    pub wrong: usize,
+    pub missing: usize,
+}
+
+// gross wpm counts every typed char, five per word, net wpm subtracts the errors that
+// were left in per minute. uncorrected errors are the wrong and missing chars under the
+// current rules. accuracy is per keystroke and missing char, corrected errors lower it too
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TypeStats {
+    pub elapsed: Duration,
+    pub gross_wpm: f64,
+    pub net_wpm: f64,
+    pub accuracy: f64,
+    pub corrected: usize,
+    pub uncorrected: usize,
+}
+
+impl TypeResults {
+    pub fn total(&self) -> usize {
+        self.correct + self.lenient + self.wrong + self.missing
+    }
 }
 
 impl TypeState {
-    pub fn from_str(value: &'static str) -> Self {
+    pub fn new(value: &str) -> Self {
+        let rules = Equivalence::default();
+        let value = rules.normalize(value);
         TypeState {
             word_index: 0,
-            data: value
-                .split(' ')
-                .map(|part| WordState {
+            data: typing_words(&value)
+                .into_iter()
+                .map(|word| WordState {
                     char_index: 0,
-                    data: part
-                        .chars()
+                    start: word.start,
+                    data: word
+                        .text
+                        .graphemes(true)
                         .enumerate()
-                        .map(|(index, reference_char)| CharState::new(index, reference_char))
+                        .map(|(index, reference)| CharState::new(index, reference))
                         .collect(),
                 })
                 .collect(),
             focus: false,
+            composition: None,
+            rules: Rc::new(rules),
+            timing: Timing::default(),
+        }
+    }
+
+    // clears everything typed but keeps the rules
+    pub fn reset(&mut self) {
+        self.data.iter_mut().for_each(|word| {
+            word.data.iter_mut().for_each(CharState::backspace);
+            word.char_index = 0;
+        });
+        self.word_index = 0;
+        self.composition = None;
+        self.timing = Timing::default();
+    }
+
+    // references follow the normalization of the rules, a grapheme stays one grapheme in
+    // either form
+    pub fn set_rules(&mut self, rules: Rc<Equivalence>) {
+        self.data
+            .iter_mut()
+            .flat_map(|word| word.data.iter_mut())
+            .for_each(|c| {
+                c.reference = rules.normalize(&c.reference);
+                c.rescore(&rules);
+            });
+        self.rules = rules;
+    }
+
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.data.get(self.word_index).map(|word| Cursor {
+            word_index: self.word_index,
+            char_index: word.char_index,
+        })
+    }
+
+    pub fn pending(&self) -> Option<&str> {
+        let word = self.data.get(self.word_index)?;
+        let current = word.data.get(word.char_index)?;
+        Some(current.pending.as_str()).filter(|pending| !pending.is_empty())
+    }
+
+    fn commit(&mut self, action: impl FnOnce(&mut CharState, &Equivalence)) -> Outcome {
+        let last_word = self.word_index + 1 == self.data.len();
+        let word = self.data.get_mut(self.word_index).unwrap();
+        let current = word.data.get_mut(word.char_index).unwrap();
+        action(current, &self.rules);
+        let quality = current.quality;
+        word.char_index += 1;
+        self.timing.typed += 1;
+        if last_word && word.is_full() {
+            self.word_index = self.data.len();
+            Outcome::Completed
+        } else if quality == Match::Exact {
+            Outcome::Correct
+        } else if quality == Match::Wrong {
+            Outcome::Wrong
+        } else {
+            Outcome::Lenient
+        }
+    }
+
+    pub fn type_char(&mut self, typed_char: char) -> Outcome {
+        self.type_grapheme(typed_char.encode_utf8(&mut [0; 4]))
+    }
+
+    pub fn type_grapheme(&mut self, typed: &str) -> Outcome {
+        if typed.is_empty() {
+            return Outcome::Ignored;
+        }
+        let Some(word) = self.data.get_mut(self.word_index) else {
+            return Outcome::Ignored;
+        };
+        if word.is_full() {
+            return Outcome::Ignored;
+        }
+        let current = word.data.get_mut(word.char_index).unwrap();
+        let mut sequence = std::mem::take(&mut current.pending);
+        sequence.push_str(typed);
+        match self.rules.transliterate(&sequence, &current.reference) {
+            Transliteration::Partial => {
+                current.pending = sequence;
+                Outcome::Pending
+            }
+            Transliteration::Complete => {
+                self.commit(|current, _rules| current.transliterated(sequence))
+            }
+            Transliteration::None => {
+                // an abandoned sequence types its first grapheme and replays the rest,
+                // the keystroke still counts when the rest does not fit into the word
+                let mut graphemes = sequence.graphemes(true);
+                let first = graphemes.next().unwrap();
+                graphemes.fold(
+                    self.commit(|current, rules| current.typed(first, rules)),
+                    |outcome, grapheme| match outcome {
+                        Outcome::Completed => outcome,
+                        _ => match self.type_grapheme(grapheme) {
+                            Outcome::Ignored => outcome,
+                            replayed => replayed,
+                        },
+                    },
+                )
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Outcome {
+        let mut outcome = Outcome::Ignored;
+        while let Some(pending) = self.pending().map(str::to_string) {
+            let word = self.data.get_mut(self.word_index).unwrap();
+            word.data.get_mut(word.char_index).unwrap().pending.clear();
+            let mut graphemes = pending.graphemes(true);
+            let first = graphemes.next().unwrap();
+            outcome = self.commit(|current, rules| current.typed(first, rules));
+            graphemes.for_each(|grapheme| {
+                if outcome != Outcome::Completed {
+                    match self.type_grapheme(grapheme) {
+                        Outcome::Ignored => {}
+                        replayed => outcome = replayed,
+                    }
+                }
+            });
+        }
+        outcome
+    }
+
+    pub fn backspace(&mut self) -> Outcome {
+        if self.is_completed() {
+            self.word_index = self.data.len().saturating_sub(1);
+            return Outcome::Moved(self.word_index);
+        }
+        let Some(word) = self.data.get_mut(self.word_index) else {
+            return Outcome::Ignored;
+        };
+        if let Some(current) = word.data.get_mut(word.char_index) {
+            if let Some((last, _)) = current.pending.grapheme_indices(true).next_back() {
+                current.pending.truncate(last);
+                return Outcome::Deleted;
+            }
+        }
+        if word.char_index > 0 {
+            word.char_index -= 1;
+            let current = word.data.get_mut(word.char_index).unwrap();
+            if current.typed.is_some() && !current.is_correct() {
+                self.timing.corrected += 1;
+            }
+            current.backspace();
+            Outcome::Deleted
+        } else if self.word_index > 0 {
+            self.word_index -= 1;
+            Outcome::Moved(self.word_index)
+        } else {
+            Outcome::Ignored
+        }
+    }
+
+    pub fn next_word(&mut self) -> Outcome {
+        if self.flush() == Outcome::Completed {
+            Outcome::Completed
+        } else if self.is_completed() {
+            Outcome::Ignored
+        } else if self.word_index + 1 < self.data.len() {
+            self.word_index += 1;
+            // the space between words is typed too
+            self.timing.typed += 1;
+            Outcome::Moved(self.word_index)
+        } else {
+            self.word_index = self.data.len();
+            Outcome::Completed
+        }
+    }
+
+    pub fn delete_word(&mut self) -> Outcome {
+        if self.is_completed() {
+            self.word_index = self.data.len().saturating_sub(1);
+        } else if self.cursor().is_some_and(|cursor| cursor.char_index == 0)
+            && self.pending().is_none()
+            && self.word_index > 0
+        {
+            self.word_index -= 1;
+        }
+        let Some(word) = self.data.get_mut(self.word_index) else {
+            return Outcome::Ignored;
+        };
+        if word.char_index == 0 && word.data.iter().all(|c| c.pending.is_empty()) {
+            return Outcome::Ignored;
+        }
+        self.timing.corrected += word
+            .data
+            .iter()
+            .filter(|c| c.typed.is_some() && !c.is_correct())
+            .count();
+        word.data.iter_mut().for_each(CharState::backspace);
+        word.char_index = 0;
+        Outcome::Deleted
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.word_index >= self.data.len()
+    }
+
+    // called with the time of every keystroke and what it did
+    pub fn record(&mut self, now: f64, outcome: Outcome) {
+        if outcome == Outcome::Ignored {
+            return;
+        }
+        if let Some(last) = self.timing.last {
+            self.timing.active_ms += (now - last).clamp(0.0, IDLE_MS);
+        }
+        self.timing.last = Some(now);
+    }
+
+    // the time until the first keystroke is not known, so it is not counted
+    pub fn stats(&self) -> TypeStats {
+        let timing = self.timing;
+        let elapsed = Duration::from_secs_f64(timing.active_ms.max(0.0) / 1000.0);
+        let results = self.results();
+        let uncorrected = results.wrong + results.missing;
+        let counted = timing.typed + results.missing;
+        let errors = timing.corrected + uncorrected;
+        let minutes = elapsed.as_secs_f64() / 60.0;
+        let (gross_wpm, net_wpm) = if minutes > 0.0 {
+            let gross = timing.typed as f64 / 5.0 / minutes;
+            (gross, (gross - uncorrected as f64 / minutes).max(0.0))
+        } else {
+            (0.0, 0.0)
+        };
+        TypeStats {
+            elapsed,
+            gross_wpm,
+            net_wpm,
+            accuracy: if timing.typed == 0 {
+                0.0
+            } else {
+                counted.saturating_sub(errors) as f64 / counted as f64
+            },
+            corrected: timing.corrected,
+            uncorrected,
         }
     }
+
+    pub fn results(&self) -> TypeResults {
+        self.data.iter().flat_map(|word| word.data.iter()).fold(
+            TypeResults::default(),
+            |mut results, c| {
+                match c.typed {
+                    None => results.missing += 1,
+                    Some(_) if c.is_lenient() => results.lenient += 1,
+                    Some(_) if c.is_correct() => results.correct += 1,
+                    Some(_) => results.wrong += 1,
+                }
+                results
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::{Leniency, Normalization};
+
+    fn type_str(state: &mut TypeState, value: &str) {
+        value.chars().for_each(|c| {
+            if c == ' ' {
+                state.next_word();
+            } else {
+                state.type_char(c);
+            }
+        });
+    }
+
+    #[test]
+    fn typing_moves_cursor() {
+        let mut state = TypeState::new("Die Preise");
+        assert_eq!(state.type_char('D'), Outcome::Correct);
+        assert_eq!(state.type_char('x'), Outcome::Wrong);
+        assert_eq!(
+            state.cursor(),
+            Some(Cursor {
+                word_index: 0,
+                char_index: 2
+            })
+        );
+        assert_eq!(state.next_word(), Outcome::Moved(1));
+        assert_eq!(
+            state.cursor(),
+            Some(Cursor {
+                word_index: 1,
+                char_index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn extra_chars_are_ignored() {
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "ab");
+        assert_eq!(state.type_char('c'), Outcome::Ignored);
+        assert!(!state.is_completed());
+    }
+
+    #[test]
+    fn last_char_completes() {
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "ab c");
+        assert_eq!(state.type_char('d'), Outcome::Completed);
+        assert!(state.is_completed());
+        assert_eq!(state.cursor(), None);
+        assert_eq!(state.type_char('e'), Outcome::Ignored);
+        assert_eq!(state.next_word(), Outcome::Ignored);
+    }
+
+    #[test]
+    fn space_on_last_word_completes() {
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "ab c");
+        assert_eq!(state.next_word(), Outcome::Completed);
+        assert!(state.is_completed());
+        assert_eq!(state.results().missing, 1);
+    }
+
+    #[test]
+    fn backspace_leaves_completed_state() {
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "ab cd");
+        assert_eq!(state.backspace(), Outcome::Moved(1));
+        assert_eq!(
+            state.cursor(),
+            Some(Cursor {
+                word_index: 1,
+                char_index: 2
+            })
+        );
+        assert_eq!(state.backspace(), Outcome::Deleted);
+        assert_eq!(state.type_char('d'), Outcome::Completed);
+    }
+
+    #[test]
+    fn backspace_returns_to_previous_word() {
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "ab c");
+        assert_eq!(state.backspace(), Outcome::Deleted);
+        assert_eq!(state.backspace(), Outcome::Moved(0));
+        assert_eq!(state.backspace(), Outcome::Deleted);
+        assert_eq!(state.results().missing, 3);
+    }
+
+    #[test]
+    fn delete_word_clears_current_then_previous() {
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "ab c");
+        assert_eq!(state.delete_word(), Outcome::Deleted);
+        assert_eq!(state.cursor().unwrap().word_index, 1);
+        assert_eq!(state.delete_word(), Outcome::Deleted);
+        assert_eq!(state.cursor().unwrap().word_index, 0);
+        assert_eq!(state.results().missing, 4);
+    }
+
+    #[test]
+    fn results_use_rules() {
+        let mut state = TypeState::new("Bürger");
+        type_str(&mut state, "burgxr");
+        assert_eq!(
+            state.results(),
+            TypeResults {
+                correct: 3,
+                lenient: 2,
+                wrong: 1,
+                missing: 0
+            }
+        );
+        assert!((state.stats().accuracy - 5.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lenient_matches_are_reported() {
+        let mut state = TypeState::new("Bär Tür");
+        assert_eq!(state.type_char('B'), Outcome::Correct);
+        state.type_char('a');
+        assert_eq!(state.type_char('r'), Outcome::Correct);
+        assert!(state.data[0].data[1].is_lenient());
+        state.next_word();
+        assert_eq!(state.type_char('t'), Outcome::Lenient);
+        assert_eq!(state.data[1].data[0].quality, Match::CaseLenient);
+        assert_eq!(state.type_char('ü'), Outcome::Correct);
+        assert_eq!(state.data[1].data[1].quality, Match::Exact);
+    }
+
+    #[test]
+    fn sample_sentence_can_be_completed() {
+        let text = "Mitunter gar in den negativen Bereich – die Versorger bekommen dann Geld dafür, dass sie Strom abnehmen.";
+        let mut state = TypeState::new(text);
+        type_str(&mut state, &text.replace('–', "-"));
+        assert!(state.is_completed());
+        assert_eq!(state.results().wrong, 0);
+    }
+
+    #[test]
+    fn decomposed_text_is_one_char_per_letter() {
+        let mut state = TypeState::new("Bu\u{308}rger");
+        assert_eq!(state.data[0].data.len(), 6);
+        assert_eq!(state.data[0].data[1].reference, "ü");
+        type_str(&mut state, "B");
+        assert_eq!(state.type_char('ü'), Outcome::Correct);
+        assert_eq!(state.type_grapheme("u\u{308}"), Outcome::Wrong);
+        assert_eq!(state.data[0].data[2].typed.as_deref(), Some("ü"));
+    }
+
+    #[test]
+    fn whitespace_does_not_create_words() {
+        let state = TypeState::new("Auf  und\tAb,\n kam ");
+        let words: Vec<String> = state
+            .data
+            .iter()
+            .map(|word| word.data.iter().map(|c| c.reference.as_str()).collect())
+            .collect();
+        assert_eq!(words, vec!["Auf", "und", "Ab,", "kam"]);
+        assert_eq!(state.data[2].start, 9);
+    }
+
+    #[test]
+    fn graphemes_are_kept_together() {
+        let mut state = TypeState::new("n\u{303}\u{301}x");
+        assert_eq!(state.data[0].data.len(), 2);
+        assert_eq!(state.type_grapheme("n\u{303}\u{301}"), Outcome::Correct);
+        assert_eq!(state.type_char('x'), Outcome::Completed);
+    }
+
+    #[test]
+    fn keystrokes_are_timed() {
+        let mut state = TypeState::new("ab cd");
+        assert_eq!(state.stats().elapsed, Duration::ZERO);
+        assert_eq!(state.stats().gross_wpm, 0.0);
+        type Keystroke = fn(&mut TypeState) -> Outcome;
+        let keystrokes: [(f64, Keystroke); 7] = [
+            (0.0, |state| state.type_char('a')),
+            (1000.0, |state| state.type_char('b')),
+            (2000.0, TypeState::next_word),
+            (3000.0, |state| state.type_char('x')),
+            (4000.0, TypeState::backspace),
+            (5000.0, |state| state.type_char('c')),
+            (6000.0, |state| state.type_char('d')),
+        ];
+        keystrokes.into_iter().for_each(|(now, keystroke)| {
+            let outcome = keystroke(&mut state);
+            state.record(now, outcome);
+        });
+        assert!(state.is_completed());
+        let outcome = state.type_char('e');
+        state.record(9000.0, outcome);
+        let stats = state.stats();
+        assert_eq!(stats.elapsed, Duration::from_secs(6));
+        assert!((stats.gross_wpm - 12.0).abs() < 1e-9);
+        assert!((stats.net_wpm - 12.0).abs() < 1e-9);
+        assert!((stats.accuracy - 5.0 / 6.0).abs() < 1e-9);
+        assert_eq!((stats.corrected, stats.uncorrected), (1, 0));
+        state.reset();
+        assert_eq!(state.timing, Timing::default());
+    }
+
+    #[test]
+    fn uncorrected_errors_lower_net_wpm() {
+        let mut state = TypeState::new("ab");
+        let outcome = state.type_char('a');
+        state.record(0.0, outcome);
+        let outcome = state.type_char('x');
+        state.record(6000.0, outcome);
+        let stats = state.stats();
+        assert!((stats.gross_wpm - 4.0).abs() < 1e-9);
+        assert_eq!(stats.net_wpm, 0.0);
+        assert_eq!((stats.corrected, stats.uncorrected), (0, 1));
+        assert_eq!(stats.accuracy, 0.5);
+
+        let mut state = TypeState::new("ab cd");
+        type_str(&mut state, "a cd");
+        let stats = state.stats();
+        assert_eq!((stats.corrected, stats.uncorrected), (0, 1));
+        assert!((stats.accuracy - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn breaks_and_reloads_do_not_count_as_typing() {
+        let mut state = TypeState::new("abc");
+        let outcome = state.type_char('a');
+        state.record(0.0, outcome);
+        let outcome = state.type_char('b');
+        state.record(60_000.0, outcome);
+        assert_eq!(state.stats().elapsed, Duration::from_secs(10));
+
+        let saved: Timing =
+            serde_json::from_str(&serde_json::to_string(&state.timing).unwrap()).unwrap();
+        assert_eq!(saved.last, None);
+        state.timing = saved;
+        let outcome = state.type_char('c');
+        state.record(3_600_000.0, outcome);
+        assert_eq!(state.stats().elapsed, Duration::from_secs(10));
+        assert_eq!(state.timing.typed, 3);
+    }
+
+    #[test]
+    fn error_counts_follow_changed_rules() {
+        let mut state = TypeState::new("Größe");
+        state.set_rules(Rc::new(Equivalence::default().with_level(Leniency::Strict)));
+        type_str(&mut state, "Gx");
+        state.backspace();
+        type_str(&mut state, "rose");
+        assert!(state.is_completed());
+        let stats = state.stats();
+        assert_eq!((stats.corrected, stats.uncorrected), (1, 2));
+        assert!((stats.accuracy - 3.0 / 6.0).abs() < 1e-9);
+
+        state.set_rules(Rc::new(Equivalence::default()));
+        let stats = state.stats();
+        assert_eq!((stats.corrected, stats.uncorrected), (1, 0));
+        assert!((stats.accuracy - 5.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn changing_rules_rescores_typed_chars() {
+        let mut state = TypeState::new("Größe");
+        type_str(&mut state, "Groß");
+        assert_eq!(state.results().correct, 3);
+        assert_eq!(state.results().lenient, 1);
+        state.set_rules(Rc::new(Equivalence::default().with_level(Leniency::Strict)));
+        assert_eq!(state.results().correct, 3);
+        assert_eq!(state.results().lenient, 0);
+        assert_eq!(state.results().wrong, 1);
+        assert_eq!(state.type_char('e'), Outcome::Completed);
+        state.set_rules(Rc::new(Equivalence::default()));
+        assert_eq!(state.results().lenient, 1);
+        assert_eq!(state.results().wrong, 0);
+    }
+
+    #[test]
+    fn transliterations_come_back_with_lenient_rules() {
+        let mut state = TypeState::new("Straße");
+        type_str(&mut state, "Strasse");
+        assert_eq!(state.results().lenient, 1);
+        state.set_rules(Rc::new(Equivalence::default().with_level(Leniency::Strict)));
+        assert_eq!(state.results().wrong, 1);
+        state.set_rules(Rc::new(Equivalence::default()));
+        assert_eq!(state.results().lenient, 1);
+        assert_eq!(state.results().wrong, 0);
+    }
+
+    #[test]
+    fn references_follow_the_normalization() {
+        let mut state = TypeState::new("Bär");
+        state.set_rules(Rc::new(Equivalence {
+            normalization: Normalization::Nfd,
+            ..Equivalence::default()
+        }));
+        assert_eq!(state.data[0].data[1].reference, "a\u{308}");
+        assert_eq!(state.data[0].data.len(), 3);
+        assert_eq!(state.type_char('B'), Outcome::Correct);
+        assert_eq!(state.type_char('ä'), Outcome::Correct);
+        assert_eq!(state.data[0].data[1].typed.as_deref(), Some("a\u{308}"));
+        state.set_rules(Rc::new(Equivalence::default()));
+        assert_eq!(state.data[0].data[1].reference, "ä");
+    }
+
+    #[test]
+    fn empty_input_is_ignored() {
+        let mut state = TypeState::new("ab");
+        assert_eq!(state.type_grapheme(""), Outcome::Ignored);
+        let mut state = TypeState::new("ä");
+        assert_eq!(state.type_grapheme(""), Outcome::Ignored);
+        assert_eq!(state.pending(), None);
+    }
+
+    #[test]
+    fn abandoned_transliterations_count_when_the_word_fills() {
+        let mut state = TypeState::new("Bä ab");
+        type_str(&mut state, "B");
+        assert_eq!(state.type_char('a'), Outcome::Pending);
+        let outcome = state.type_char('x');
+        assert_eq!(outcome, Outcome::Lenient);
+        state.record(0.0, outcome);
+        assert_eq!(state.timing.typed, 2);
+        assert_eq!(state.timing.last, Some(0.0));
+    }
+
+    #[test]
+    fn transliteration_resolves_to_one_char() {
+        let mut state = TypeState::new("Straße Bär");
+        type_str(&mut state, "Stra");
+        assert_eq!(state.type_char('s'), Outcome::Pending);
+        assert_eq!(state.pending(), Some("s"));
+        assert_eq!(state.type_char('s'), Outcome::Lenient);
+        assert_eq!(state.pending(), None);
+        type_str(&mut state, "e Ba");
+        assert_eq!(state.pending(), Some("a"));
+        assert_eq!(state.type_char('e'), Outcome::Lenient);
+        assert_eq!(state.type_char('r'), Outcome::Completed);
+        assert_eq!(state.results().correct, 7);
+        assert_eq!(state.results().lenient, 2);
+        let c = &state.data[0].data[4];
+        assert_eq!(c.transliteration.as_deref(), Some("ss"));
+        assert_eq!(c.typed_text(), "ss");
+    }
+
+    #[test]
+    fn abandoned_transliteration_replays_chars() {
+        let mut state = TypeState::new("Bär");
+        type_str(&mut state, "Ba");
+        assert_eq!(state.type_char('r'), Outcome::Completed);
+        assert_eq!(state.results().correct, 2);
+        assert_eq!(state.results().lenient, 1);
+
+        let mut state = TypeState::new("Bär");
+        type_str(&mut state, "ba");
+        assert_eq!(state.backspace(), Outcome::Deleted);
+        assert_eq!(state.pending(), None);
+        assert_eq!(state.cursor().unwrap().char_index, 1);
+    }
+
+    #[test]
+    fn next_word_flushes_pending() {
+        let mut state = TypeState::new("Bä ab");
+        type_str(&mut state, "Ba");
+        assert_eq!(state.next_word(), Outcome::Moved(1));
+        assert_eq!(state.results().lenient, 1);
+
+        let mut state = TypeState::new("ab Bä");
+        type_str(&mut state, "ab Ba");
+        assert_eq!(state.next_word(), Outcome::Completed);
+        assert_eq!(state.results().correct, 3);
+        assert_eq!(state.results().lenient, 1);
+    }
 }
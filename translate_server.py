@@ -1,7 +1,20 @@
 from transformers import MarianMTModel, MarianTokenizer
-model_name = "Helsinki-NLP/opus-mt-de-en"
-tokenizer = MarianTokenizer.from_pretrained(model_name)
-model = MarianMTModel.from_pretrained(model_name)
+
+models = {}
+
+# the languages the app offers, equivalence::LANGUAGES and lesson::TARGETS, so a client
+# cannot make the server download and keep any other model
+SOURCES = {"de", "fr", "es", "pl", "nl"}
+TARGETS = {"en", "de", "fr", "es", "pl", "nl"}
+
+# the client caches translations under this model name, MARIAN_MODELS in translate.rs
+def load(source, target):
+    model_name = f"Helsinki-NLP/opus-mt-{source}-{target}"
+    if model_name not in models:
+        models[model_name] = (MarianTokenizer.from_pretrained(model_name), MarianMTModel.from_pretrained(model_name))
+    return models[model_name]
+
+load("de", "en")
 
 import json
 from flask import Flask, request, jsonify
@@ -9,8 +22,21 @@ from flask import Flask, request, jsonify
 app = Flask(__name__)
 @app.route('/translate', methods=['POST'])
 def translate():
-    src_text = json.loads(request.data)["src"]
-    translated = model.generate(**tokenizer(src_text, return_tensors="pt", padding=True))
-    return jsonify({'translated': [tokenizer.decode(t, skip_special_tokens=True) for t in translated]})
+    data = json.loads(request.data)
+    src_text = data["src"]
+    source, target = data.get("source", "de"), data.get("target", "en")
+    if source not in SOURCES or target not in TARGETS or source == target:
+        return jsonify({'error': 'unsupported language pair'}), 400
+    try:
+        tokenizer, model = load(source, target)
+    except OSError:
+        return jsonify({'error': 'no model for this language pair'}), 404
+    variants = max(1, int(data.get("variants", 1)))
+    translated = model.generate(**tokenizer(src_text, return_tensors="pt", padding=True), num_beams=max(4, variants), num_return_sequences=variants)
+    decoded = [tokenizer.decode(t, skip_special_tokens=True) for t in translated]
+    # n-best lists come back flat, variants of one sentance are next to each other
+    # blank variants are dropped, the client reports a sentance without any as failed
+    grouped = [list(dict.fromkeys(v for v in decoded[i:i + variants] if v.strip())) for i in range(0, len(decoded), variants)]
+    return jsonify({'translated': [v[0] if v else "" for v in grouped], 'variants': grouped})
 print ("start application")
 app.run(debug=False,host="0.0.0.0")